@@ -0,0 +1,267 @@
+//! Command line argument parsing.
+
+use std::path::PathBuf;
+
+use raytracer::BUILT_IN_SCENES;
+
+pub const USAGE: &str = "\
+Usage: raytracer [OPTIONS] [SCENE]
+
+Renders SCENE, which is either the path of a scene file or the name of a
+built-in scene: book-cover (the default), basic, cornell or cornell-smoke.
+
+Options:
+  -W, --width <PIXELS>     Image width, overriding the scene's
+  -H, --height <PIXELS>    Image height, overriding the scene's
+  -s, --samples <N>        Anti-aliasing samples per pixel
+  -d, --max-depth <N>      Maximum number of times a ray may bounce
+      --seed <N>           Seed from which all randomness is derived
+  -j, --threads <N>        Number of render threads, defaults to the number of
+                           available cores
+  -o, --output <FILE>      Where to save the image, may be given more than
+                           once; the format (png or ppm) is inferred from the
+                           extension, defaults to raytracer.png
+      --stats              Print statistics about the scene's bounding
+                           volume hierarchy before rendering
+  -h, --help               Print this message
+";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+}
+
+pub struct Output {
+    pub path: PathBuf,
+    pub format: ImageFormat,
+}
+
+pub enum SceneSource {
+    BuiltIn(String),
+    File(PathBuf),
+}
+
+pub struct Args {
+    pub scene: SceneSource,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub n_samples: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub seed: Option<u64>,
+    pub n_threads: Option<usize>,
+    pub outputs: Vec<Output>,
+    pub stats: bool,
+}
+
+pub enum CliError {
+    /// The user asked for the usage message.
+    Help,
+    Invalid(String),
+}
+
+fn value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, CliError> {
+    args.next()
+        .ok_or_else(|| CliError::Invalid(format!("missing value for {}", flag)))
+}
+
+fn positive<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<usize, CliError> {
+    let v = value(args, flag)?;
+    match v.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(CliError::Invalid(format!(
+            "{} expects a positive integer, got '{}'",
+            flag, v
+        ))),
+    }
+}
+
+fn output(path: String) -> Result<Output, CliError> {
+    let path = PathBuf::from(path);
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+    let format = match ext.as_ref().map(|ext| &ext[..]) {
+        Some("png") => ImageFormat::Png,
+        Some("ppm") => ImageFormat::Ppm,
+        _ => {
+            return Err(CliError::Invalid(format!(
+                "cannot infer image format of '{}', expected a .png or .ppm extension",
+                path.display()
+            )))
+        }
+    };
+    Ok(Output { path, format })
+}
+
+/// Parses the command line arguments, excluding the program name.
+pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Args, CliError> {
+    let mut parsed = Args {
+        scene: SceneSource::BuiltIn(String::from(BUILT_IN_SCENES[0])),
+        width: None,
+        height: None,
+        n_samples: None,
+        max_depth: None,
+        seed: None,
+        n_threads: None,
+        outputs: Vec::new(),
+        stats: false,
+    };
+    let mut scene = None;
+
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "-h" | "--help" => return Err(CliError::Help),
+            "-W" | "--width" => parsed.width = Some(positive(&mut args, &arg)?),
+            "-H" | "--height" => parsed.height = Some(positive(&mut args, &arg)?),
+            "-s" | "--samples" => parsed.n_samples = Some(positive(&mut args, &arg)?),
+            "-d" | "--max-depth" => parsed.max_depth = Some(positive(&mut args, &arg)?),
+            "-j" | "--threads" => parsed.n_threads = Some(positive(&mut args, &arg)?),
+            "--seed" => {
+                let v = value(&mut args, &arg)?;
+                match v.parse() {
+                    Ok(seed) => parsed.seed = Some(seed),
+                    Err(_) => {
+                        return Err(CliError::Invalid(format!(
+                            "--seed expects a non-negative integer, got '{}'",
+                            v
+                        )));
+                    }
+                }
+            }
+            "-o" | "--output" => parsed.outputs.push(output(value(&mut args, &arg)?)?),
+            "--stats" => parsed.stats = true,
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(CliError::Invalid(format!("unknown option '{}'", arg)));
+            }
+            _ => {
+                if scene.is_some() {
+                    return Err(CliError::Invalid(format!("unexpected argument '{}'", arg)));
+                }
+                scene = Some(arg);
+            }
+        }
+    }
+
+    if let Some(scene) = scene {
+        parsed.scene = if BUILT_IN_SCENES.contains(&&scene[..]) {
+            SceneSource::BuiltIn(scene)
+        } else {
+            SceneSource::File(PathBuf::from(scene))
+        };
+    }
+    if parsed.outputs.is_empty() {
+        parsed.outputs.push(output(String::from("raytracer.png"))?);
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Args, CliError> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn parsed(args: &[&str]) -> Args {
+        match parse_args(args) {
+            Ok(args) => args,
+            Err(CliError::Help) => panic!("{:?} asked for help", args),
+            Err(CliError::Invalid(message)) => panic!("{:?} rejected: {}", args, message),
+        }
+    }
+
+    fn invalid(args: &[&str]) -> String {
+        match parse_args(args) {
+            Err(CliError::Invalid(message)) => message,
+            Err(CliError::Help) => panic!("{:?} asked for help", args),
+            Ok(_) => panic!("{:?} accepted", args),
+        }
+    }
+
+    #[test]
+    fn output_formats_are_inferred_from_the_extension() {
+        let args = parsed(&["-o", "a.png", "--output", "dir/b.PPM", "-o", "c.Png"]);
+        let outputs: Vec<_> = args
+            .outputs
+            .iter()
+            .map(|output| (output.path.to_str().unwrap(), output.format))
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![
+                ("a.png", ImageFormat::Png),
+                ("dir/b.PPM", ImageFormat::Ppm),
+                ("c.Png", ImageFormat::Png),
+            ]
+        );
+
+        let outputs = parsed(&[]).outputs;
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].path, PathBuf::from("raytracer.png"));
+        assert_eq!(outputs[0].format, ImageFormat::Png);
+
+        for &path in ["image.jpg", "image", "png"].iter() {
+            assert_eq!(
+                invalid(&["-o", path]),
+                format!(
+                    "cannot infer image format of '{}', expected a .png or .ppm extension",
+                    path
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn sizes_must_be_positive_integers() {
+        for &flag in ["-W", "--width", "-H", "--height", "-s", "--samples"].iter() {
+            for &value in ["0", "-1", "ten", "1.5", ""].iter() {
+                assert_eq!(
+                    invalid(&[flag, value]),
+                    format!("{} expects a positive integer, got '{}'", flag, value)
+                );
+            }
+            assert_eq!(invalid(&[flag]), format!("missing value for {}", flag));
+        }
+
+        let args = parsed(&["-W", "640", "--height", "480", "-s", "16", "-d", "8", "-j", "2"]);
+        assert_eq!(args.width, Some(640));
+        assert_eq!(args.height, Some(480));
+        assert_eq!(args.n_samples, Some(16));
+        assert_eq!(args.max_depth, Some(8));
+        assert_eq!(args.n_threads, Some(2));
+        assert_eq!(parsed(&["--seed", "0"]).seed, Some(0));
+        assert_eq!(
+            invalid(&["--seed", "-3"]),
+            "--seed expects a non-negative integer, got '-3'"
+        );
+    }
+
+    #[test]
+    fn unknown_flags_are_rejected() {
+        assert_eq!(invalid(&["--fast"]), "unknown option '--fast'");
+        assert_eq!(invalid(&["basic", "-x"]), "unknown option '-x'");
+        match parse_args(&["-W", "10", "--help"]) {
+            Err(CliError::Help) => {}
+            _ => panic!("--help did not ask for help"),
+        }
+    }
+
+    #[test]
+    fn scenes_are_built_in_or_files() {
+        let scene = |args: &[&str]| match parsed(args).scene {
+            SceneSource::BuiltIn(name) => format!("built-in {}", name),
+            SceneSource::File(path) => format!("file {}", path.display()),
+        };
+        assert_eq!(scene(&[]), "built-in book-cover");
+        assert_eq!(scene(&["cornell-smoke"]), "built-in cornell-smoke");
+        assert_eq!(scene(&["-s", "4", "basic"]), "built-in basic");
+        assert_eq!(scene(&["scenes/basic.toml"]), "file scenes/basic.toml");
+        // Names are only those of built-in scenes if they match exactly.
+        assert_eq!(scene(&["Cornell"]), "file Cornell");
+        assert_eq!(scene(&["-"]), "file -");
+        assert_eq!(invalid(&["basic", "cornell"]), "unexpected argument 'cornell'");
+    }
+}
@@ -0,0 +1,16 @@
+//! A ray tracer based on Peter Shirley's Ray Tracing in One Weekend.
+//!
+//! Scenes are built from objects implementing `Hit`, made of materials
+//! implementing `Scatter`, either in code or by loading a scene file with
+//! `load_scene`, and are rendered into a `Framebuffer` with a `Renderer`.
+
+extern crate rand;
+extern crate image;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+
+mod raytracer;
+
+pub use raytracer::*;
@@ -0,0 +1,78 @@
+use raytracer::ray::Ray;
+use raytracer::vec3::Vec3;
+
+/// An axis-aligned bounding box, described by its two opposite corners.
+#[derive(Debug, Copy, Clone)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Returns the smallest box enclosing both `a` and `b`.
+    pub fn surrounding(a: Aabb, b: Aabb) -> Aabb {
+        Aabb {
+            min: Vec3 {
+                x: a.min.x.min(b.min.x),
+                y: a.min.y.min(b.min.y),
+                z: a.min.z.min(b.min.z),
+            },
+            max: Vec3 {
+                x: a.max.x.max(b.max.x),
+                y: a.max.y.max(b.max.y),
+                z: a.max.z.max(b.max.z),
+            },
+        }
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Returns the index of the axis along which the box is the longest, where
+    /// 0, 1 and 2 stand for x, y and z respectively.
+    pub fn longest_axis(&self) -> usize {
+        let d = self.max - self.min;
+        if d.x > d.y && d.x > d.z {
+            0
+        } else if d.y > d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Determines whether the ray passes through the box within the `min` and
+    /// `max` interval.
+    pub fn hit(&self, ray: &Ray, min: f32, max: f32) -> bool {
+        self.clip(ray, min, max).is_some()
+    }
+
+    /// Returns the part of the `min` and `max` interval along the ray that
+    /// lies within the box, if any, using the slab method: the ray is clipped
+    /// against the pair of planes bounding each axis and the box is hit only
+    /// if the three resulting intervals overlap.
+    pub fn clip(&self, ray: &Ray, min: f32, max: f32) -> Option<(f32, f32)> {
+        let mut min = min;
+        let mut max = max;
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            min = if t0 > min { t0 } else { min };
+            max = if t1 < max { t1 } else { max };
+            if max <= min {
+                return None;
+            }
+        }
+        Some((min, max))
+    }
+}
@@ -0,0 +1,208 @@
+//! What rays that leave the scene without hitting anything see, which also
+//! lights the scene from all around it.
+
+use std::f32::consts::PI;
+
+use raytracer::ray::Ray;
+use raytracer::vec3::*;
+
+/// The light arriving from infinitely far away in each direction. Like `Hit`,
+/// backgrounds are shared between render threads.
+pub trait Background: Send + Sync {
+    /// The light arriving along the reverse of `ray`'s direction.
+    fn color(&self, ray: &Ray) -> Vec3;
+}
+
+/// A uniform color. Black leaves the scene lit by emissive materials only.
+#[derive(Debug, Copy, Clone)]
+pub struct SolidBackground {
+    pub color: Vec3,
+}
+
+impl Background for SolidBackground {
+    fn color(&self, _: &Ray) -> Vec3 {
+        self.color
+    }
+}
+
+/// A gradient blending linearly with the height of the direction looked in,
+/// from `down`, seen looking straight down, to `up`, seen looking straight up.
+#[derive(Debug, Copy, Clone)]
+pub struct GradientBackground {
+    pub down: Vec3,
+    pub up: Vec3,
+}
+
+impl GradientBackground {
+    /// A simple sky, from white below to light blue overhead.
+    pub fn sky() -> Self {
+        Self {
+            down: Vec3 { x: 1.0, y: 1.0, z: 1.0 },
+            up: Vec3 { x: 0.5, y: 0.7, z: 1.0 },
+        }
+    }
+}
+
+impl Background for GradientBackground {
+    fn color(&self, ray: &Ray) -> Vec3 {
+        // Get unit vector of ray's direction so -1 < y < 1 and scale that
+        // value to 0 < y < 1.
+        let t = 0.5 * (ray.direction.to_unit().y + 1.0);
+        // Linear interpolation:
+        // blended_val = (1 - t) * start_val + t * end_val.
+        (1.0 - t) * self.down + t * self.up
+    }
+}
+
+/// The scale from the sky's luminance, in thousands of candela per square
+/// meter, to the renderer's units, which puts the zenith of a clear midday
+/// sky at about half the brightness of a white surface lit by a light of 1.
+const SKY_SCALE: f32 = 0.05;
+
+/// How far below the horizon, in radians, the sun goes before the sky is dark.
+const TWILIGHT: f32 = 6.0 * PI / 180.0;
+
+/// The clear daytime sky of the analytic model of Preetham et al., "A
+/// Practical Analytic Model for Daylight", lit by the sun in the direction
+/// `sun`. The sky is blue overhead at midday, and brightens and reddens towards
+/// the horizon and around the sun as it sets. The sun's own disk, too small to
+/// be found by the rays scattered towards it, is not drawn.
+///
+/// Below the horizon, the sky is as seen at the horizon. Once the sun has
+/// set, the model no longer holds, and the sky keeps its colors at sunset
+/// while it fades to black over the twilight.
+#[derive(Debug, Copy, Clone)]
+pub struct PreethamSky {
+    sun: Vec3,
+    /// The luminance and chromaticity (Y, x and y) at the zenith, divided by
+    /// their Perez distributions there, so that multiplying them by the
+    /// distributions in any direction gives their values in that direction.
+    zenith: [f32; 3],
+    /// The coefficients of the Perez distribution of each of Y, x and y.
+    perez: [[f32; 5]; 3],
+    /// The factor by which the sky dims after sunset.
+    twilight: f32,
+}
+
+impl PreethamSky {
+    /// Creates the sky lit by the sun in the direction `sun`, with the haze of
+    /// the atmosphere given by its `turbidity`, the ratio of its thickness to
+    /// that of clear air. It is about 2 for a clear sky and the model holds up
+    /// to about 10, for a hazy one.
+    pub fn new(sun: Vec3, turbidity: f32) -> Self {
+        let sun = sun.to_unit();
+        let elevation = sun.y.clamp(-1.0, 1.0).asin();
+        let twilight = (1.0 + elevation / TWILIGHT).clamp(0.0, 1.0);
+        let theta = (PI / 2.0 - elevation).min(PI / 2.0);
+
+        let t = turbidity;
+        let coefficients = |c: [[f32; 2]; 5]| c.map(|[a, b]| a * t + b);
+        let perez = [
+            coefficients([
+                [0.1787, -1.4630],
+                [-0.3554, 0.4275],
+                [-0.0227, 5.3251],
+                [0.1206, -2.5771],
+                [-0.0670, 0.3703],
+            ]),
+            coefficients([
+                [-0.0193, -0.2592],
+                [-0.0665, 0.0008],
+                [-0.0004, 0.2125],
+                [-0.0641, -0.8989],
+                [-0.0033, 0.0452],
+            ]),
+            coefficients([
+                [-0.0167, -0.2608],
+                [-0.0950, 0.0092],
+                [-0.0079, 0.2102],
+                [-0.0441, -1.6537],
+                [-0.0109, 0.0529],
+            ]),
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let (t1, t2, t3) = (theta, theta * theta, theta * theta * theta);
+        let chromaticity = |c: [[f32; 4]; 3]| {
+            let row = |r: [f32; 4]| r[0] * t3 + r[1] * t2 + r[2] * t1 + r[3];
+            t * t * row(c[0]) + t * row(c[1]) + row(c[2])
+        };
+        let x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let mut zenith = [luminance, x, y];
+        for (z, coefficients) in zenith.iter_mut().zip(perez.iter()) {
+            *z /= perez_distribution(coefficients, 0.0, theta);
+        }
+        Self {
+            sun,
+            zenith,
+            perez,
+            twilight,
+        }
+    }
+}
+
+impl Background for PreethamSky {
+    fn color(&self, ray: &Ray) -> Vec3 {
+        let direction = ray.direction.to_unit();
+        // Directions below the horizon are treated as if on it.
+        let theta = direction.y.clamp(0.0, 1.0).acos();
+        let gamma = dot(direction, self.sun).clamp(-1.0, 1.0).acos();
+        let [luminance, x, y] = [0, 1, 2]
+            .map(|i| self.zenith[i] * perez_distribution(&self.perez[i], theta, gamma));
+
+        // Convert from the chromaticity and luminance to CIE XYZ, then to
+        // linear sRGB.
+        let luminance = luminance.max(0.0) * SKY_SCALE * self.twilight;
+        if y <= 0.0 {
+            return Vec3 { x: 0.0, y: 0.0, z: 0.0 };
+        }
+        let cx = x / y * luminance;
+        let cz = (1.0 - x - y) / y * luminance;
+        let cy = luminance;
+        Vec3 {
+            x: (3.2406 * cx - 1.5372 * cy - 0.4986 * cz).max(0.0),
+            y: (-0.9689 * cx + 1.8758 * cy + 0.0415 * cz).max(0.0),
+            z: (0.0557 * cx - 0.2040 * cy + 1.0570 * cz).max(0.0),
+        }
+    }
+}
+
+/// The Perez distribution of the sky's luminance (or chromaticity), relative
+/// to its value at the zenith, in the direction `theta` from the zenith and
+/// `gamma` from the sun.
+fn perez_distribution(c: &[f32; 5], theta: f32, gamma: f32) -> f32 {
+    // Directions on the horizon would divide by zero.
+    let cos_theta = theta.cos().max(1e-3);
+    let cos_gamma = gamma.cos();
+    (1.0 + c[0] * (c[1] / cos_theta).exp())
+        * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * cos_gamma * cos_gamma)
+}
+
+/// Returns the direction of the sun at the solar time `hour`, from 0 to 24, on
+/// the `day` of the year, from 1 to 365, seen from the `latitude` in degrees,
+/// positive to the north. The sun rises towards +X, the east, and sets towards
+/// -X, while +Y points up and +Z to the south.
+pub fn sun_direction(hour: f32, day: f32, latitude: f32) -> Vec3 {
+    let declination = (23.44 * PI / 180.0) * (2.0 * PI * (284.0 + day) / 365.0).sin();
+    let hour_angle = (hour - 12.0) * PI / 12.0;
+    let latitude = latitude * PI / 180.0;
+    let (sin_lat, cos_lat) = latitude.sin_cos();
+    let (sin_dec, cos_dec) = declination.sin_cos();
+    let north = cos_lat * sin_dec - sin_lat * cos_dec * hour_angle.cos();
+    Vec3 {
+        x: -cos_dec * hour_angle.sin(),
+        y: sin_lat * sin_dec + cos_lat * cos_dec * hour_angle.cos(),
+        z: -north,
+    }
+}
@@ -0,0 +1,108 @@
+use raytracer::aabb::Aabb;
+use raytracer::hit::*;
+use raytracer::light::Light;
+use raytracer::ray::Ray;
+use raytracer::sah_bvh::*;
+
+/// A bounding volume hierarchy over a list of `Hit` objects.
+///
+/// The objects are arranged in a binary tree in which each node stores the
+/// bounding box of all the objects beneath it, so a ray only needs to be tested
+/// against the objects whose enclosing boxes it passes through, rather than
+/// against every object in the scene. The tree is a `SahBvh` built with its
+/// default settings.
+pub struct Bvh {
+    tree: SahBvh,
+}
+
+impl Bvh {
+    pub fn new(objects: Vec<Box<dyn Hit>>) -> Self {
+        Self {
+            tree: SahBvh::new(objects, SahInfo::default()),
+        }
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.tree.stats()
+    }
+}
+
+impl Hit for Bvh {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        self.tree.hit(ray, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.tree.bounding_box()
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        self.tree.lights(lights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use raytracer::render::RenderSettings;
+    use raytracer::sampler::Sampler;
+    use raytracer::scatter::Scatter;
+    use raytracer::scenes::book_cover_scene;
+    use raytracer::vec3::Vec3;
+
+    #[test]
+    fn hits_match_the_list() {
+        // The same objects, shared between a plain list and a tree.
+        let objects: Vec<Arc<dyn Hit>> = book_cover_scene(RenderSettings::default())
+            .objects
+            .into_iter()
+            .map(Arc::from)
+            .collect();
+        let boxed = || {
+            objects
+                .iter()
+                .map(|object| Box::new(Arc::clone(object)) as Box<dyn Hit>)
+                .collect::<Vec<_>>()
+        };
+        let list = boxed();
+        let bvh = Bvh::new(boxed());
+
+        let material = |material: &dyn Scatter| material as *const dyn Scatter as *const u8;
+        let mut sampler = Sampler::new(5);
+        let mut n_hits = 0;
+        for _ in 0..20_000 {
+            // Rays from all over the field of spheres, in all directions.
+            let origin = Vec3 {
+                x: 24.0 * sampler.next_f32() - 12.0,
+                y: 4.0 * sampler.next_f32(),
+                z: 24.0 * sampler.next_f32() - 12.0,
+            };
+            let ray = Ray {
+                origin,
+                direction: sampler.unit_vector(),
+                time: 0.0,
+            };
+            match (list.hit(&ray, 0.001, f32::MAX), bvh.hit(&ray, 0.001, f32::MAX)) {
+                (None, None) => {}
+                (Some(expected), Some(actual)) => {
+                    assert_eq!(expected.t, actual.t);
+                    assert!(material(expected.material) == material(actual.material));
+                    n_hits += 1;
+                }
+                (expected, actual) => panic!(
+                    "list hit at {:?}, BVH at {:?}",
+                    expected.map(|rec| rec.t),
+                    actual.map(|rec| rec.t)
+                ),
+            }
+        }
+        assert!(n_hits > 1000);
+    }
+}
@@ -1,11 +1,6 @@
-extern crate rand;
-
 use raytracer::vec3::*;
 use raytracer::ray::Ray;
-
-use rand::Rng;
-
-use image::RgbImage;
+use raytracer::sampler::Sampler;
 
 pub struct Camera {
     origin: Vec3,
@@ -23,8 +18,11 @@ pub struct Camera {
     /// The larger the lens, the more defocus blur there will be around the
     /// focus area, which subsequently will be smaller.
     lens_radius: f32,
+    shutter_open: f32,
+    shutter_close: f32,
 }
 
+#[derive(Debug, Copy, Clone)]
 pub struct CameraInfo {
     /// The origin of the camera.
     pub look_from: Vec3,
@@ -42,6 +40,11 @@ pub struct CameraInfo {
     pub aperture: f32,
     /// The distance from the origin that describes the focused point.
     pub focus_distance: f32,
+    /// The times at which the shutter opens and closes. Rays are cast at times
+    /// spread evenly between the two, so that objects moving in the meantime
+    /// are blurred. If they are equal, there is no motion blur.
+    pub shutter_open: f32,
+    pub shutter_close: f32,
 }
 
 impl Camera {
@@ -55,6 +58,8 @@ impl Camera {
             y0: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
             //z0: Vec3 { x: 0.0, y: 0.0, z: 1.0 },
             lens_radius: 0.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
@@ -88,34 +93,33 @@ impl Camera {
             // the focus distance.
             horizontal: x0 * info.focus_distance * 2.0 * half_width,
             vertical: y0 * info.focus_distance * 2.0 * half_height,
-            x0: x0,
-            y0: y0,
+            x0,
+            y0,
             //z0: z0,
             lens_radius: info.aperture / 2.0,
+            shutter_open: info.shutter_open,
+            shutter_close: info.shutter_close,
         }
     }
 
-    pub fn ray(&self, h: f32, v: f32) -> Ray {
+    pub fn ray(&self, h: f32, v: f32, sampler: &mut Sampler) -> Ray {
         // Offset ray's origin so that it's on a disk around `look_from`.
-        let rd = self.lens_radius * rand_in_unit_disk();
+        let rd = self.lens_radius * sampler.in_unit_disk();
         let offset = self.x0 * rd.x + self.y0 * rd.y;
+        // Only draw a sample for the time if the shutter stays open, so that
+        // still images are unaffected by it.
+        let time = if self.shutter_close > self.shutter_open {
+            self.shutter_open + sampler.next_f32() * (self.shutter_close - self.shutter_open)
+        } else {
+            self.shutter_open
+        };
         Ray {
             origin: self.origin + offset,
             direction: self.lower_left_corner
                 + h * self.horizontal
                 + v * self.vertical
                 - self.origin - offset,
-        }
-    }
-}
-
-fn rand_in_unit_disk() -> Vec3 {
-    let mut rng = rand::thread_rng();
-    loop {
-        let p = 2.0 * Vec3 { x: rng.gen(), y: rng.gen(), z: 0.0 }
-            - Vec3 { x: 1.0, y: 1.0, z: 0.0 };
-        if dot(p, p) < 1.0 {
-            return p;
+            time,
         }
     }
 }
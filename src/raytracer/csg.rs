@@ -0,0 +1,183 @@
+//! Constructive solid geometry: solids combined by set operations on the
+//! space they enclose.
+//!
+//! The operands must be closed, with their normals pointing out of them, so
+//! that whether a ray enters or leaves one at a hit can be told from the
+//! normal there. Following the ray through the hits of both operands then
+//! gives the intervals along it that lie within the combined solid, and the
+//! nearest hit at which it crosses into or out of it is the one returned,
+//! with the material of the operand whose surface it is.
+//!
+//! Combined solids are not sampled as lights, since their operands' lights
+//! may be partly cut away, but their light is still found by the rays
+//! scattered towards them.
+
+use raytracer::aabb::Aabb;
+use raytracer::hit::*;
+use raytracer::ray::Ray;
+use raytracer::vec3::*;
+
+/// The space within either of two solids.
+pub struct Union<A: Hit, B: Hit> {
+    pub left: A,
+    pub right: B,
+}
+
+/// The space within both of two solids, such as a lens made of two spheres.
+pub struct Intersection<A: Hit, B: Hit> {
+    pub left: A,
+    pub right: B,
+}
+
+/// The space within the `left` solid but not the `right` one, which is cut
+/// away from it, such as a hole drilled through a block. The surfaces left
+/// behind by the cut are those of the `right` solid, turned inside out.
+pub struct Difference<A: Hit, B: Hit> {
+    pub left: A,
+    pub right: B,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Operation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl Operation {
+    /// Whether a point within the left solid or not, as given by `in_left`,
+    /// and the right one, as given by `in_right`, lies within the result.
+    fn contains(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            Operation::Union => in_left || in_right,
+            Operation::Intersection => in_left && in_right,
+            Operation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+impl<A: Hit, B: Hit> Hit for Union<A, B> {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        combine(Operation::Union, &self.left, &self.right, ray, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let left = self.left.bounding_box()?;
+        let right = self.right.bounding_box()?;
+        Some(Aabb::surrounding(left, right))
+    }
+}
+
+impl<A: Hit, B: Hit> Hit for Intersection<A, B> {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        combine(Operation::Intersection, &self.left, &self.right, ray, min, max)
+    }
+
+    /// Encloses the overlap of the operands' boxes, or the box of whichever
+    /// of them is bounded.
+    fn bounding_box(&self) -> Option<Aabb> {
+        match (self.left.bounding_box(), self.right.bounding_box()) {
+            (Some(a), Some(b)) => {
+                let min = Vec3 {
+                    x: a.min.x.max(b.min.x),
+                    y: a.min.y.max(b.min.y),
+                    z: a.min.z.max(b.min.z),
+                };
+                let max = Vec3 {
+                    x: a.max.x.min(b.max.x),
+                    y: a.max.y.min(b.max.y),
+                    z: a.max.z.min(b.max.z),
+                };
+                // Disjoint boxes leave an empty box, which no ray hits.
+                Some(Aabb { min, max })
+            }
+            (Some(a), None) => Some(a),
+            (None, b) => b,
+        }
+    }
+}
+
+impl<A: Hit, B: Hit> Hit for Difference<A, B> {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        combine(Operation::Difference, &self.left, &self.right, ray, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.left.bounding_box()
+    }
+}
+
+/// Returns the nearest hit at which the ray crosses the surface of the solid
+/// given by combining `left` and `right` with `operation`.
+fn combine<'a, A: Hit, B: Hit>(
+    operation: Operation,
+    left: &'a A,
+    right: &'a B,
+    ray: &Ray,
+    min: f32,
+    max: f32,
+) -> Option<HitRecord<'a>> {
+    // Whether the ray starts within each operand is only known from their
+    // hits beyond `max` if it leaves them there, so the hits are searched for
+    // all along the ray, and `max` only limits the hit returned.
+    let mut left_hits = Vec::new();
+    left.hit_all(ray, min, f32::MAX, &mut left_hits);
+    let mut right_hits = Vec::new();
+    right.hit_all(ray, min, f32::MAX, &mut right_hits);
+
+    // Leaving a solid at its first hit means the ray started within it.
+    let leaves = |rec: &HitRecord| !rec.front_face;
+    let mut in_left = left_hits.first().is_some_and(&leaves);
+    let mut in_right = right_hits.first().is_some_and(&leaves);
+    let mut inside = operation.contains(in_left, in_right);
+
+    // Merge the hits of both in the order the ray meets them, until one takes
+    // the ray into or out of the result.
+    let mut left_hits = left_hits.into_iter().peekable();
+    let mut right_hits = right_hits.into_iter().peekable();
+    loop {
+        let from_left = match (left_hits.peek(), right_hits.peek()) {
+            (Some(l), Some(r)) => l.t <= r.t,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => return None,
+        };
+        let mut rec = if from_left {
+            let rec = left_hits.next()?;
+            in_left = !leaves(&rec);
+            rec
+        } else {
+            let rec = right_hits.next()?;
+            in_right = !leaves(&rec);
+            rec
+        };
+        let was_inside = inside;
+        inside = operation.contains(in_left, in_right);
+        if inside != was_inside {
+            if rec.t >= max {
+                return None;
+            }
+            // The normal already faces the ray, but the inside of the right
+            // solid is the outside of the result.
+            if operation == Operation::Difference && !from_left {
+                rec.front_face = !rec.front_face;
+            }
+            return Some(rec);
+        }
+    }
+}
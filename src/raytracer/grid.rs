@@ -0,0 +1,264 @@
+//! Dense grids of density values describing heterogeneous media, such as
+//! clouds or explosions.
+//!
+//! Grids are loaded from either of two simple formats. The text format lists
+//! whitespace separated numbers: the grid's size along x, y and z, followed by
+//! its values with x varying fastest and z slowest. Anything after a `#` on a
+//! line is a comment. For example, a grid of 2x1x1 voxels:
+//!
+//! ```text
+//! # nx ny nz
+//! 2 1 1
+//! 0.0 1.0
+//! ```
+//!
+//! The binary format holds the same, in little endian: the magic bytes `GRID`,
+//! the size as three 32-bit unsigned integers and the values as 32-bit floats.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use raytracer::vec3::Vec3;
+
+/// The bytes with which binary grid files start.
+const MAGIC: &[u8; 4] = b"GRID";
+
+#[derive(Debug)]
+pub enum GridError {
+    Io { path: PathBuf, error: io::Error },
+    Parse { path: PathBuf, message: String },
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GridError::Io { ref path, ref error } => write!(f, "{}: {}", path.display(), error),
+            GridError::Parse { ref path, ref message } => {
+                write!(f, "{}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl Error for GridError {}
+
+/// A grid of non-negative density values, one at the center of each voxel of
+/// a box divided into `nx` by `ny` by `nz` voxels.
+pub struct DensityGrid {
+    nx: usize,
+    ny: usize,
+    nz: usize,
+    /// The values, with x varying fastest and z slowest.
+    values: Vec<f32>,
+    max: f32,
+}
+
+impl DensityGrid {
+    /// Creates a grid from its size and values, which are ordered with x
+    /// varying fastest and z slowest. Returns an error message if the values
+    /// do not fill the grid or are not all finite and non-negative.
+    pub fn new(nx: usize, ny: usize, nz: usize, values: Vec<f32>) -> Result<Self, String> {
+        if nx == 0 || ny == 0 || nz == 0 {
+            return Err(format!("grid size must be positive, got {}x{}x{}", nx, ny, nz));
+        }
+        let len = nx
+            .checked_mul(ny)
+            .and_then(|n| n.checked_mul(nz))
+            .ok_or_else(|| format!("grid size {}x{}x{} is too large", nx, ny, nz))?;
+        if values.len() != len {
+            return Err(format!(
+                "expected {} values for a {}x{}x{} grid, got {}",
+                len,
+                nx,
+                ny,
+                nz,
+                values.len()
+            ));
+        }
+        if let Some(x) = values.iter().find(|x| !(x.is_finite() && **x >= 0.0)) {
+            return Err(format!("densities must be finite and not negative, got {}", x));
+        }
+        let max = values.iter().cloned().fold(0.0, f32::max);
+        Ok(Self { nx, ny, nz, values, max })
+    }
+
+    /// Loads the grid in the text or binary format from the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GridError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|error| GridError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let parsed = if bytes.starts_with(MAGIC) {
+            parse_binary(&bytes[MAGIC.len()..])
+        } else {
+            parse_text(&bytes)
+        };
+        parsed.map_err(|message| GridError::Parse {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+
+    /// The largest density in the grid.
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    /// Returns the density at `p`, given in coordinates from 0 to 1 across the
+    /// grid, trilinearly interpolated between the values of the eight voxels
+    /// around it. Beyond the centers of the outermost voxels, their values
+    /// extend to the grid's edges.
+    pub fn density(&self, p: Vec3) -> f32 {
+        let axis = |x: f32, n: usize| {
+            let x = (x * n as f32 - 0.5).clamp(0.0, (n - 1) as f32);
+            let i = (x as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), x - i as f32)
+        };
+        let (x0, x1, tx) = axis(p.x, self.nx);
+        let (y0, y1, ty) = axis(p.y, self.ny);
+        let (z0, z1, tz) = axis(p.z, self.nz);
+        let at = |x: usize, y: usize, z: usize| self.values[(z * self.ny + y) * self.nx + x];
+        let lerp = |a: f32, b: f32, t: f32| a + t * (b - a);
+        let y_lerp = |z: usize| {
+            lerp(
+                lerp(at(x0, y0, z), at(x1, y0, z), tx),
+                lerp(at(x0, y1, z), at(x1, y1, z), tx),
+                ty,
+            )
+        };
+        lerp(y_lerp(z0), y_lerp(z1), tz)
+    }
+}
+
+fn parse_text(bytes: &[u8]) -> Result<DensityGrid, String> {
+    let text = std::str::from_utf8(bytes).map_err(|_| String::from("invalid UTF-8 text"))?;
+    let mut numbers = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| line.split_whitespace());
+    let mut size = [0; 3];
+    for n in size.iter_mut() {
+        let word = numbers.next().ok_or_else(|| String::from("missing grid size"))?;
+        *n = word
+            .parse()
+            .map_err(|_| format!("invalid grid size '{}'", word))?;
+    }
+    let values = numbers
+        .map(|word| word.parse().map_err(|_| format!("invalid density '{}'", word)))
+        .collect::<Result<Vec<f32>, String>>()?;
+    DensityGrid::new(size[0], size[1], size[2], values)
+}
+
+fn parse_binary(bytes: &[u8]) -> Result<DensityGrid, String> {
+    if bytes.len() < 12 {
+        return Err(String::from("missing grid size"));
+    }
+    let word = |i: usize| [bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]];
+    let size = |i: usize| u32::from_le_bytes(word(i)) as usize;
+    let (nx, ny, nz) = (size(0), size(4), size(8));
+    let data = &bytes[12..];
+    if data.len() % 4 != 0 {
+        return Err(String::from("truncated density value"));
+    }
+    let values = (0..data.len() / 4)
+        .map(|i| f32::from_le_bytes(word(12 + 4 * i)))
+        .collect();
+    DensityGrid::new(nx, ny, nz, values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(size: [u32; 3], values: &[f32]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        for n in size.iter() {
+            bytes.extend_from_slice(&n.to_le_bytes());
+        }
+        for x in values {
+            bytes.extend_from_slice(&x.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn point(x: f32, y: f32, z: f32) -> Vec3 {
+        Vec3 { x, y, z }
+    }
+
+    #[test]
+    fn text_grids_are_parsed() {
+        let grid = parse_text(b"# size\n2 1 2 # nx ny nz\n0 1\n2.5 3\n").unwrap();
+        assert_eq!((grid.nx, grid.ny, grid.nz), (2, 1, 2));
+        assert_eq!(grid.values, vec![0.0, 1.0, 2.5, 3.0]);
+        assert_eq!(grid.max(), 3.0);
+    }
+
+    #[test]
+    fn invalid_text_grids_are_rejected() {
+        let error = |text: &[u8]| parse_text(text).err().unwrap();
+        assert_eq!(error(b"2 2"), "missing grid size");
+        assert_eq!(error(b"2 x 1"), "invalid grid size 'x'");
+        assert_eq!(error(b"2 1 1 0.5"), "expected 2 values for a 2x1x1 grid, got 1");
+        assert_eq!(error(b"2 1 1 0.5 0.5 0.5"), "expected 2 values for a 2x1x1 grid, got 3");
+        assert_eq!(error(b"0 1 1"), "grid size must be positive, got 0x1x1");
+        assert_eq!(error(b"1 1 1 -1"), "densities must be finite and not negative, got -1");
+        assert_eq!(error(b"1 1 1 inf"), "densities must be finite and not negative, got inf");
+        assert_eq!(error(b"1 1 1 y"), "invalid density 'y'");
+    }
+
+    #[test]
+    fn binary_grids_are_parsed() {
+        let bytes = binary([1, 2, 1], &[0.25, 4.0]);
+        let grid = parse_binary(&bytes[MAGIC.len()..]).unwrap();
+        assert_eq!((grid.nx, grid.ny, grid.nz), (1, 2, 1));
+        assert_eq!(grid.values, vec![0.25, 4.0]);
+    }
+
+    #[test]
+    fn invalid_binary_grids_are_rejected() {
+        let error = |bytes: &[u8]| parse_binary(&bytes[MAGIC.len()..]).err().unwrap();
+        let bytes = binary([2, 1, 1], &[1.0, 2.0]);
+        assert_eq!(error(&bytes[..10]), "missing grid size");
+        assert_eq!(error(&bytes[..bytes.len() - 1]), "truncated density value");
+        assert_eq!(error(&bytes[..bytes.len() - 4]), "expected 2 values for a 2x1x1 grid, got 1");
+        let bytes = binary([1, 1, 2], &[1.0, -2.0]);
+        assert_eq!(error(&bytes), "densities must be finite and not negative, got -2");
+    }
+
+    #[test]
+    fn densities_are_interpolated_between_voxel_centers() {
+        // A 2x2x2 grid whose value is 1 + x + 2y + 4z at the voxel (x, y, z),
+        // and so trilinear throughout.
+        let values = (0..8).map(|i| 1.0 + i as f32).collect();
+        let grid = DensityGrid::new(2, 2, 2, values).unwrap();
+        let expected = |x: f32, y: f32, z: f32| 1.0 + x + 2.0 * y + 4.0 * z;
+        // The voxels' centers lie a quarter and three quarters of the way
+        // across the grid.
+        assert_eq!(grid.density(point(0.25, 0.25, 0.25)), 1.0);
+        assert_eq!(grid.density(point(0.75, 0.25, 0.25)), 2.0);
+        assert_eq!(grid.density(point(0.75, 0.75, 0.75)), 8.0);
+        let d = grid.density(point(0.5, 0.5, 0.5));
+        assert!((d - expected(0.5, 0.5, 0.5)).abs() < 1e-6);
+        let d = grid.density(point(0.375, 0.5, 0.625));
+        assert!((d - expected(0.25, 0.5, 0.75)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn densities_extend_to_the_edges() {
+        let grid = DensityGrid::new(2, 1, 1, vec![1.0, 3.0]).unwrap();
+        // Beyond the outermost centers, and outside of the grid, the values
+        // of the outermost voxels are used.
+        assert_eq!(grid.density(point(0.0, 0.5, 0.5)), 1.0);
+        assert_eq!(grid.density(point(0.1, 0.0, 1.0)), 1.0);
+        assert_eq!(grid.density(point(1.0, 0.5, 0.5)), 3.0);
+        assert_eq!(grid.density(point(2.0, -1.0, 0.5)), 3.0);
+        assert_eq!(grid.density(point(0.5, 0.5, 0.5)), 2.0);
+        // A grid of a single voxel is uniform.
+        let grid = DensityGrid::new(1, 1, 1, vec![0.5]).unwrap();
+        assert_eq!(grid.density(point(0.0, 0.3, 1.0)), 0.5);
+    }
+}
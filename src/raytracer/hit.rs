@@ -1,6 +1,10 @@
+use std::sync::Arc;
+
+use raytracer::aabb::Aabb;
+use raytracer::light::Light;
 use raytracer::ray::Ray;
 use raytracer::scatter::Scatter;
-use raytracer::vec3::Vec3;
+use raytracer::vec3::*;
 
 /// Describes how a ray hit an object (implementing the `Hit` trait).
 pub struct HitRecord<'a> {
@@ -9,21 +13,144 @@ pub struct HitRecord<'a> {
     pub t: f32,
     /// The point at which ray hits the object.
     pub point: Vec3,
-    /// The surface normal.
+    /// The unit surface normal, always facing against the ray, so that
+    /// materials scatter light back to the side of the surface it came from.
     pub normal: Vec3,
+    /// Whether the ray hit the outside of the surface, in which case `normal`
+    /// is the outward normal, and otherwise its opposite.
+    pub front_face: bool,
+    /// The surface coordinates of the point, by which textures are mapped onto
+    /// the object.
+    pub u: f32,
+    pub v: f32,
     /// The material hit by this ray. This is a reference to the material stored
     /// in object hit by the ray.
     pub material: &'a dyn Scatter,
 }
 
+impl<'a> HitRecord<'a> {
+    /// The normal pointing out of the surface, whichever side the ray hit.
+    pub fn outward_normal(&self) -> Vec3 {
+        if self.front_face {
+            self.normal
+        } else {
+            -1.0 * self.normal
+        }
+    }
+}
+
+/// Returns the normal facing against `ray` given the surface's `outward`
+/// normal, along with whether the ray hit the outside of the surface, for
+/// the `normal` and `front_face` of a `HitRecord`.
+pub fn face_normal(ray: &Ray, outward: Vec3) -> (Vec3, bool) {
+    if dot(ray.direction, outward) > 0.0 {
+        (-1.0 * outward, false)
+    } else {
+        (outward, true)
+    }
+}
+
 /// A trait that implementors can use to describe how a `Ray` may hit them.
-pub trait Hit {
+///
+/// Objects are shared between the threads rendering the image, hence the
+/// `Send` and `Sync` bounds.
+pub trait Hit: Send + Sync {
     /// Calculates whether the ray hits this object. For convenience, a valid
     /// hit interval range may also be added with with the `min` and `max`
     /// parameters, wich can be used to limit the area that counts as a hit.  If
     /// this object is hit by the ray, the details about the hit are stored in
     /// a `HitRecord`.
     fn hit<'a, 'b: 'a>(&'b self, ray: &Ray, min: f32, max: f32) -> Option<HitRecord<'a>>;
+
+    /// Adds every hit of the ray within the `min` and `max` interval to
+    /// `hits`, in the order in which the ray meets them, rather than just the
+    /// nearest one. This is what lets constructive solid geometry, such as a
+    /// `Difference`, tell where the ray is inside of its operands.
+    ///
+    /// By default, the object is hit again and again, each time starting
+    /// from the last hit found.
+    fn hit_all<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+        hits: &mut Vec<HitRecord<'a>>,
+    ) {
+        let mut min = min;
+        while let Some(rec) = self.hit(ray, min, max) {
+            min = rec.t;
+            hits.push(rec);
+        }
+    }
+
+    /// Returns the axis-aligned box enclosing this object, used to build
+    /// acceleration structures such as `Bvh`. Objects that are unbounded
+    /// return `None`.
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    /// Adds the lights of the emissive objects among this one to `lights`, so
+    /// that they can be sampled directly.
+    fn lights(&self, _lights: &mut Vec<Box<dyn Light>>) {}
+}
+
+impl<T: Hit + ?Sized> Hit for Box<T> {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        (**self).hit(ray, min, max)
+    }
+
+    fn hit_all<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+        hits: &mut Vec<HitRecord<'a>>,
+    ) {
+        (**self).hit_all(ray, min, max, hits)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        (**self).lights(lights)
+    }
+}
+
+/// Objects shared with `Arc` can be placed in the world many times, e.g. by
+/// `Instance`s, while only being kept in memory once.
+impl<T: Hit + ?Sized> Hit for Arc<T> {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        (**self).hit(ray, min, max)
+    }
+
+    fn hit_all<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+        hits: &mut Vec<HitRecord<'a>>,
+    ) {
+        (**self).hit_all(ray, min, max, hits)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        (**self).lights(lights)
+    }
 }
 
 /// 'Hit' trait implementation for a list of 'Hit' objects.
@@ -44,4 +171,19 @@ impl Hit for Vec<Box<dyn Hit>> {
         }
         record
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut bbox: Option<Aabb> = None;
+        for hitable in self.iter() {
+            let b = hitable.bounding_box()?;
+            bbox = Some(bbox.map_or(b, |bbox| Aabb::surrounding(bbox, b)));
+        }
+        bbox
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        for hitable in self.iter() {
+            hitable.lights(lights);
+        }
+    }
 }
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use raytracer::aabb::Aabb;
+use raytracer::hit::*;
+use raytracer::light::Light;
+use raytracer::mat4::Mat4;
+use raytracer::ray::Ray;
+use raytracer::scatter::Scatter;
+use raytracer::transform::Transformed;
+
+/// A placement of geometry that is shared with other instances, such as a
+/// mesh repeated throughout a scene, with its own transformation and
+/// optionally its own material.
+///
+/// The instances of a scene are kept in one acceleration structure, while the
+/// primitives of each piece of geometry are kept in another, such as the
+/// hierarchy of a `TriangleMesh`, or a `SahBvh` grouping several objects. So
+/// the memory used by a scene grows with the amount of unique geometry in it,
+/// rather than with the number of times it is placed.
+pub struct Instance {
+    object: Transformed<Arc<dyn Hit>>,
+    /// Replaces the materials of the geometry if set.
+    material: Option<Arc<dyn Scatter>>,
+}
+
+impl Instance {
+    /// Places `geometry` in the world with `transform`, as in `Transformed`.
+    pub fn new(
+        geometry: Arc<dyn Hit>,
+        transform: Mat4,
+        material: Option<Arc<dyn Scatter>>,
+    ) -> Self {
+        Self {
+            object: Transformed::new(geometry, transform),
+            material,
+        }
+    }
+}
+
+impl Hit for Instance {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        let mut rec = self.object.hit(ray, min, max)?;
+        if let Some(ref material) = self.material {
+            rec.material = &**material;
+        }
+        Some(rec)
+    }
+
+    fn hit_all<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+        hits: &mut Vec<HitRecord<'a>>,
+    ) {
+        let first = hits.len();
+        self.object.hit_all(ray, min, max, hits);
+        if let Some(ref material) = self.material {
+            for rec in hits[first..].iter_mut() {
+                rec.material = &**material;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.object.bounding_box()
+    }
+
+    /// The geometry's lights are only sampled if its own materials are used,
+    /// as it is those that determine which of its parts emit light.
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        if self.material.is_none() {
+            self.object.lights(lights);
+        }
+    }
+}
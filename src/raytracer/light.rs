@@ -0,0 +1,51 @@
+use raytracer::hit::Hit;
+use raytracer::sampler::Sampler;
+use raytracer::vec3::Vec3;
+
+/// The shape of an emissive object, which can be sampled to send rays
+/// directly towards it rather than waiting for randomly scattered rays to
+/// find it.
+pub trait Light: Send + Sync {
+    /// Returns a random direction from `origin` towards the light.
+    fn sample_direction(&self, origin: Vec3, sampler: &mut Sampler) -> Vec3;
+
+    /// The probability density, with respect to solid angle, of
+    /// `sample_direction` returning `direction` from `origin`. This is zero if
+    /// a ray in that direction misses the light.
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f32;
+}
+
+/// All the lights in a scene, sampled by picking one of them uniformly.
+pub struct LightList {
+    lights: Vec<Box<dyn Light>>,
+}
+
+impl LightList {
+    /// Collects the lights of the emissive objects in `world`.
+    pub fn new<T: Hit + ?Sized>(world: &T) -> Self {
+        let mut lights = Vec::new();
+        world.lights(&mut lights);
+        Self { lights }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    pub fn sample_direction(&self, origin: Vec3, sampler: &mut Sampler) -> Option<Vec3> {
+        if self.lights.is_empty() {
+            return None;
+        }
+        let i = (sampler.next_f32() * self.lights.len() as f32) as usize;
+        let light = &self.lights[i.min(self.lights.len() - 1)];
+        Some(light.sample_direction(origin, sampler))
+    }
+
+    pub fn pdf(&self, origin: Vec3, direction: Vec3) -> f32 {
+        if self.lights.is_empty() {
+            return 0.0;
+        }
+        let sum: f32 = self.lights.iter().map(|l| l.pdf(origin, direction)).sum();
+        sum / self.lights.len() as f32
+    }
+}
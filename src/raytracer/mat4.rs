@@ -0,0 +1,159 @@
+use std::ops::Mul;
+
+use raytracer::vec3::*;
+
+/// A 4x4 matrix describing an affine transformation of points and vectors in
+/// homogeneous coordinates, stored row by row.
+///
+/// Transformations are combined by multiplying their matrices, with the one on
+/// the right applied first, so `translation * rotation` rotates and then
+/// translates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn identity() -> Self {
+        Self {
+            m: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut t = Self::identity();
+        t.m[0][3] = offset.x;
+        t.m[1][3] = offset.y;
+        t.m[2][3] = offset.z;
+        t
+    }
+
+    /// Scales by `factors` along each axis, from the origin.
+    pub fn scaling(factors: Vec3) -> Self {
+        let mut t = Self::identity();
+        t.m[0][0] = factors.x;
+        t.m[1][1] = factors.y;
+        t.m[2][2] = factors.z;
+        t
+    }
+
+    /// Rotates counter-clockwise by `degrees` around `axis`, as seen looking
+    /// from the tip of the axis towards the origin.
+    pub fn rotation(axis: Vec3, degrees: f32) -> Self {
+        // Rodrigues' rotation formula.
+        let a = axis.to_unit();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let k = 1.0 - cos;
+        Self {
+            m: [
+                [
+                    cos + a.x * a.x * k,
+                    a.x * a.y * k - a.z * sin,
+                    a.x * a.z * k + a.y * sin,
+                    0.0,
+                ],
+                [
+                    a.y * a.x * k + a.z * sin,
+                    cos + a.y * a.y * k,
+                    a.y * a.z * k - a.x * sin,
+                    0.0,
+                ],
+                [
+                    a.z * a.x * k - a.y * sin,
+                    a.z * a.y * k + a.x * sin,
+                    cos + a.z * a.z * k,
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut t = Self::identity();
+        for i in 0..4 {
+            for j in 0..4 {
+                t.m[i][j] = self.m[j][i];
+            }
+        }
+        t
+    }
+
+    /// Returns the inverse transformation, or `None` if the matrix is
+    /// singular, e.g. because it scales some axis by zero.
+    pub fn inverse(&self) -> Option<Self> {
+        // Gauss-Jordan elimination with partial pivoting, carried out in
+        // double precision to keep round-off from accumulating.
+        let mut a = self.m.map(|row| row.map(|x| x as f64));
+        let mut inv = Self::identity().m.map(|row| row.map(|x| x as f64));
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+            for row in 0..4 {
+                if row != col {
+                    let factor = a[row][col];
+                    for j in 0..4 {
+                        a[row][j] -= factor * a[col][j];
+                        inv[row][j] -= factor * inv[col][j];
+                    }
+                }
+            }
+        }
+        Some(Self {
+            m: inv.map(|row| row.map(|x| x as f32)),
+        })
+    }
+
+    /// The determinant of the linear part of the transformation, i.e. the
+    /// factor by which it scales volumes.
+    pub fn linear_determinant(&self) -> f32 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        self.transform_vector(p) + Vec3 { x: self.m[0][3], y: self.m[1][3], z: self.m[2][3] }
+    }
+
+    /// Transforms a direction or offset, to which translation does not apply.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3 {
+            x: m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            y: m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            z: m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        }
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut t = Mat4 { m: [[0.0; 4]; 4] };
+        for i in 0..4 {
+            for j in 0..4 {
+                t.m[i][j] = (0..4).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+        t
+    }
+}
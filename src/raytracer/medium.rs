@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use raytracer::aabb::Aabb;
+use raytracer::grid::DensityGrid;
+use raytracer::hit::*;
+use raytracer::ray::Ray;
+use raytracer::sampler::Sampler;
+use raytracer::scatter::*;
+use raytracer::texture::Texture;
+use raytracer::vec3::Vec3;
+
+/// A volume of constant density, such as fog or smoke, filling a closed
+/// `boundary`. Rays passing through it scatter at distances that are
+/// exponentially distributed, so the denser the medium, the sooner they
+/// scatter, and their chance of passing through decreases exponentially with
+/// the length of their path through it.
+///
+/// A ray that scatters hits the medium at the point where it does, with the
+/// `material` describing in which directions it scatters, such as a `Volume`
+/// with a phase function. The normal of such a hit is meaningless.
+pub struct ConstantMedium<T: Hit> {
+    pub boundary: T,
+    /// The probability per unit of distance of a ray scattering.
+    pub density: f32,
+    pub material: Arc<dyn Scatter>,
+}
+
+impl<T: Hit> Hit for ConstantMedium<T> {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        // Find where the ray enters and leaves the boundary, even if it starts
+        // inside of it, and clip that to the interval searched.
+        let enter = self.boundary.hit(ray, -f32::MAX, f32::MAX)?;
+        let leave = self.boundary.hit(ray, enter.t, f32::MAX)?;
+        let t0 = enter.t.max(min);
+        let t1 = leave.t.min(max);
+        if t0 >= t1 {
+            return None;
+        }
+
+        // `hit` is not given a sampler, so the distance is drawn from one
+        // derived from the ray.
+        let mut sampler = Sampler::for_ray(ray);
+        let speed = ray.direction.len();
+        let distance = -(1.0 - sampler.next_f32()).ln() / self.density;
+        if distance > (t1 - t0) * speed {
+            return None;
+        }
+        let t = t0 + distance / speed;
+        Some(HitRecord {
+            t,
+            point: ray.point_at(t),
+            normal: Vec3 { x: 1.0, y: 0.0, z: 0.0 },
+            front_face: true,
+            u: 0.0,
+            v: 0.0,
+            material: &*self.material,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
+
+/// A volume whose density varies through space, given by a `DensityGrid`
+/// stretched across the box `bounds`. Other orientations are had by
+/// transforming it.
+///
+/// Where rays scatter is found by delta tracking: tentative collisions are
+/// sampled as if the medium everywhere had its largest density, and each is
+/// accepted with the probability of the actual density there relative to the
+/// largest one, rejected collisions being fictitious ones which the ray
+/// passes through unaffected.
+///
+/// The hits at which rays scatter have their `u` surface coordinate set to
+/// the density there, relative to the largest one, so that textures can vary
+/// with it, such as an explosion glowing brighter at its dense core.
+pub struct GridMedium {
+    grid: Arc<DensityGrid>,
+    bounds: Aabb,
+    /// The factor by which the grid's values are scaled to give the
+    /// probability per unit of distance of a ray scattering.
+    density: f32,
+    material: Arc<dyn Scatter>,
+}
+
+impl GridMedium {
+    /// Fills `bounds` with `grid`, whose values are multiplied by `density`.
+    /// Light scatters by `material`, such as a `Volume`, which may be given
+    /// a colored albedo to tint the medium. With `emission`, the medium
+    /// also glows with the color it gives at each point at which a ray
+    /// scatters, which is more often where the medium is denser.
+    pub fn new(
+        grid: Arc<DensityGrid>,
+        bounds: Aabb,
+        density: f32,
+        material: Arc<dyn Scatter>,
+        emission: Option<Box<dyn Texture>>,
+    ) -> Self {
+        let material = match emission {
+            Some(emission) => Arc::new(EmissiveMedium { material, emission }),
+            None => material,
+        };
+        Self {
+            grid,
+            bounds,
+            density,
+            material,
+        }
+    }
+
+    /// Returns the density at `point`, relative to the grid's largest value.
+    fn relative_density(&self, point: Vec3) -> f32 {
+        let p = (point - self.bounds.min) / (self.bounds.max - self.bounds.min);
+        self.grid.density(p) / self.grid.max()
+    }
+}
+
+impl Hit for GridMedium {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        let max_density = self.density * self.grid.max();
+        if max_density <= 0.0 {
+            return None;
+        }
+        let (t0, t1) = self.bounds.clip(ray, min, max)?;
+
+        // `hit` is not given a sampler, so the distances are drawn from one
+        // derived from the ray.
+        let mut sampler = Sampler::for_ray(ray);
+        let speed = ray.direction.len();
+        let mut t = t0;
+        loop {
+            t += -(1.0 - sampler.next_f32()).ln() / (max_density * speed);
+            if t >= t1 {
+                return None;
+            }
+            let point = ray.point_at(t);
+            let density = self.relative_density(point);
+            if sampler.next_f32() < density {
+                return Some(HitRecord {
+                    t,
+                    point,
+                    normal: Vec3 { x: 1.0, y: 0.0, z: 0.0 },
+                    front_face: true,
+                    u: density,
+                    v: 0.0,
+                    material: &*self.material,
+                });
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+/// The material of a glowing medium, which scatters light as its own material
+/// does and adds its emission at every collision.
+struct EmissiveMedium {
+    material: Arc<dyn Scatter>,
+    emission: Box<dyn Texture>,
+}
+
+impl Scatter for EmissiveMedium {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        self.material.scatter(ray, hit, sampler)
+    }
+
+    fn emitted(&self, hit: &HitRecord) -> Vec3 {
+        self.emission.value(hit.u, hit.v, hit.point)
+    }
+
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: Vec3) -> Vec3 {
+        self.material.eval(ray, hit, direction)
+    }
+
+    fn pdf(&self, ray: &Ray, hit: &HitRecord, direction: Vec3) -> f32 {
+        self.material.pdf(ray, hit, direction)
+    }
+}
@@ -1,15 +1,67 @@
+pub use self::aabb::*;
+pub use self::background::*;
+pub use self::bvh::Bvh;
 pub use self::camera::{Camera, CameraInfo};
+pub use self::csg::*;
+pub use self::grid::{DensityGrid, GridError};
 pub use self::hit::*;
+pub use self::instance::Instance;
+pub use self::light::*;
+pub use self::mat4::Mat4;
+pub use self::medium::{ConstantMedium, GridMedium};
+pub use self::obj::{load_obj, ObjError, ObjGroup};
+pub use self::onb::Onb;
+pub use self::perlin::Perlin;
+pub use self::phase::*;
+pub use self::plane::Plane;
+pub use self::quadric::*;
 pub use self::ray::*;
+pub use self::rect::*;
+pub use self::render::*;
 pub use self::rgb::*;
+pub use self::roots::{solve_quadratic, solve_quartic, Roots};
+pub use self::sah_bvh::*;
+pub use self::sampler::Sampler;
 pub use self::scatter::*;
+pub use self::scene::*;
+pub use self::scenes::*;
 pub use self::sphere::*;
+pub use self::texture::*;
+pub use self::torus::Torus;
+pub use self::transform::Transformed;
+pub use self::triangle::*;
 pub use self::vec3::*;
 
+mod aabb;
+mod background;
+mod bvh;
 mod camera;
+mod csg;
+mod grid;
 mod hit;
+mod instance;
+mod light;
+mod mat4;
+mod medium;
+mod obj;
+mod onb;
+mod perlin;
+mod phase;
+mod plane;
+mod quadric;
 mod ray;
+mod rect;
+mod render;
 mod rgb;
+mod roots;
+mod sah_bvh;
+mod sampler;
 mod scatter;
+mod scene;
+mod scenes;
 mod sphere;
+mod texture;
+mod torus;
+mod transform;
+mod triangle;
 mod vec3;
@@ -0,0 +1,672 @@
+//! A loader for Wavefront OBJ models and their MTL material libraries.
+//!
+//! Only polygonal geometry is supported: vertex positions, normals, texture
+//! coordinates and faces, the latter of which are triangulated as a fan if
+//! they have more than three vertices. Faces are split into separate meshes
+//! whenever a new group, object or material starts. Of the texture maps in MTL
+//! materials, only the diffuse color map is supported.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use raytracer::scatter::*;
+use raytracer::texture::*;
+use raytracer::triangle::*;
+use raytracer::vec3::Vec3;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io { path: PathBuf, error: io::Error },
+    Parse { path: PathBuf, line: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ObjError::Io { ref path, ref error } => write!(f, "{}: {}", path.display(), error),
+            ObjError::Parse { ref path, line, ref message } => {
+                write!(f, "{}:{}: {}", path.display(), line, message)
+            }
+        }
+    }
+}
+
+impl Error for ObjError {}
+
+/// A named part of a model that uses a single material.
+pub struct ObjGroup {
+    pub name: String,
+    pub mesh: TriangleMesh,
+}
+
+/// The parameters of an MTL material that are mapped onto this renderer's
+/// materials.
+#[derive(Clone)]
+struct MtlMaterial {
+    /// `Kd`
+    diffuse: Vec3,
+    /// `map_Kd`, which takes the place of `diffuse` if present.
+    diffuse_map: Option<ImageTexture>,
+    /// `Ks`
+    specular: Vec3,
+    /// `Ns`, in the range 0 to 1000.
+    shininess: f32,
+    /// `d`, or one minus `Tr`.
+    dissolve: f32,
+    /// `Ni`
+    optical_density: f32,
+    illum: u32,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: Vec3 { x: 0.8, y: 0.8, z: 0.8 },
+            diffuse_map: None,
+            specular: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+            shininess: 0.0,
+            dissolve: 1.0,
+            optical_density: 1.5,
+            illum: 2,
+        }
+    }
+}
+
+impl MtlMaterial {
+    /// Picks the material that best matches the MTL parameters: transparent
+    /// materials become `Dielectric`, those with reflection enabled or
+    /// dominated by their specular component become `Reflective`, and all
+    /// others `Lambertian`.
+    fn to_scatter(&self) -> Arc<dyn Scatter> {
+        let max = |v: Vec3| v.x.max(v.y).max(v.z);
+        let is_transparent = self.dissolve < 1.0 || [4, 6, 7, 9].contains(&self.illum);
+        let is_reflective =
+            [3, 5, 8].contains(&self.illum) || max(self.specular) > max(self.diffuse);
+        if is_transparent {
+            Arc::new(Dielectric {
+                refraction_index: self.optical_density,
+            })
+        } else if is_reflective {
+            // Higher specular exponents give tighter highlights, i.e. a
+            // smoother surface.
+            let fuzz = 1.0 - (self.shininess / 1000.0).clamp(0.0, 1.0).sqrt();
+            Arc::new(Reflective {
+                albedo: Box::new(SolidColor { color: self.specular }),
+                fuzz,
+            })
+        } else {
+            let albedo: Box<dyn Texture> = match self.diffuse_map {
+                Some(ref map) => Box::new(map.clone()),
+                None => Box::new(SolidColor { color: self.diffuse }),
+            };
+            Arc::new(Lambertian { albedo })
+        }
+    }
+}
+
+/// The vertices and faces of the mesh that is currently being parsed.
+struct MeshBuilder {
+    name: String,
+    material: MtlMaterial,
+    /// Maps each distinct combination of position, texture coordinate and
+    /// normal indices to the index of the mesh vertex created for it.
+    vertex_indices: HashMap<(usize, Option<usize>, Option<usize>), usize>,
+    positions: Vec<Vec3>,
+    normals: Vec<Option<Vec3>>,
+    uvs: Vec<Option<[f32; 2]>>,
+    indices: Vec<[usize; 3]>,
+}
+
+impl MeshBuilder {
+    fn new(name: String, material: MtlMaterial) -> Self {
+        Self {
+            name,
+            material,
+            vertex_indices: HashMap::new(),
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn build(self) -> ObjGroup {
+        // Vertex attributes are only used if every vertex has them.
+        let normals: Option<Vec<Vec3>> = self.normals.into_iter().collect();
+        let uvs: Option<Vec<[f32; 2]>> = self.uvs.into_iter().collect();
+        ObjGroup {
+            name: self.name,
+            mesh: TriangleMesh::new(MeshData {
+                positions: self.positions,
+                normals,
+                uvs,
+                indices: self.indices,
+                material: self.material.to_scatter(),
+            }),
+        }
+    }
+}
+
+/// Cursor over the lines of an OBJ or MTL file that tracks where errors occur.
+struct Parser {
+    path: PathBuf,
+    line: usize,
+}
+
+impl Parser {
+    fn error<T, S: Into<String>>(&self, message: S) -> Result<T, ObjError> {
+        Err(ObjError::Parse {
+            path: self.path.clone(),
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn floats(&self, args: &[&str], n: usize) -> Result<Vec<f32>, ObjError> {
+        if args.len() < n {
+            return self.error(format!("expected {} numbers, got {}", n, args.len()));
+        }
+        args.iter()
+            .take(n)
+            .map(|arg| match arg.parse::<f32>() {
+                Ok(x) if x.is_finite() => Ok(x),
+                _ => self.error(format!("invalid number '{}'", arg)),
+            })
+            .collect()
+    }
+
+    fn vec3(&self, args: &[&str]) -> Result<Vec3, ObjError> {
+        let v = self.floats(args, 3)?;
+        Ok(Vec3 { x: v[0], y: v[1], z: v[2] })
+    }
+
+    /// Resolves a 1-based, or if negative, relative to the end, OBJ index into
+    /// a list of `len` elements.
+    fn index(&self, arg: &str, len: usize, what: &str) -> Result<usize, ObjError> {
+        let i = match arg.parse::<i64>() {
+            Ok(i) => i,
+            Err(_) => return self.error(format!("invalid {} index '{}'", what, arg)),
+        };
+        let resolved = if i < 0 { len as i64 + i } else { i - 1 };
+        if i == 0 || resolved < 0 || resolved >= len as i64 {
+            self.error(format!("{} index {} out of range", what, i))
+        } else {
+            Ok(resolved as usize)
+        }
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, ObjError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|error| ObjError::Io { path: path.to_path_buf(), error })
+}
+
+/// Iterates over the statements of an OBJ or MTL file, calling `f` with the
+/// parser, the keyword and the arguments of each non-empty line.
+fn for_each_statement<F>(path: &Path, mut f: F) -> Result<(), ObjError>
+where
+    F: FnMut(&mut Parser, &str, &[&str]) -> Result<(), ObjError>,
+{
+    let reader = open(path)?;
+    let mut parser = Parser {
+        path: path.to_path_buf(),
+        line: 0,
+    };
+    for line in reader.lines() {
+        parser.line += 1;
+        let line = line.map_err(|error| ObjError::Io { path: path.to_path_buf(), error })?;
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => &line[..],
+        };
+        let mut words = line.split_whitespace();
+        if let Some(keyword) = words.next() {
+            let args: Vec<&str> = words.collect();
+            f(&mut parser, keyword, &args)?;
+        }
+    }
+    Ok(())
+}
+
+fn load_mtl(path: &Path, materials: &mut HashMap<String, MtlMaterial>) -> Result<(), ObjError> {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut current: Option<(String, MtlMaterial)> = None;
+    for_each_statement(path, |parser, keyword, args| {
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return parser.error("missing material name");
+            }
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            current = Some((args.join(" "), MtlMaterial::default()));
+            return Ok(());
+        }
+
+        let material = match current {
+            Some((_, ref mut material)) => material,
+            None => return parser.error(format!("'{}' before any 'newmtl'", keyword)),
+        };
+        match keyword {
+            "Kd" => material.diffuse = parser.vec3(args)?,
+            "Ks" => material.specular = parser.vec3(args)?,
+            "Ns" => material.shininess = parser.floats(args, 1)?[0],
+            "d" => material.dissolve = parser.floats(args, 1)?[0],
+            "Tr" => material.dissolve = 1.0 - parser.floats(args, 1)?[0],
+            "Ni" => {
+                let ni = parser.floats(args, 1)?[0];
+                if ni <= 0.0 {
+                    return parser.error(format!("optical density must be positive, got {}", ni));
+                }
+                material.optical_density = ni;
+            }
+            "illum" => {
+                material.illum = match args.first().and_then(|arg| arg.parse().ok()) {
+                    Some(illum) => illum,
+                    None => return parser.error("invalid illumination model"),
+                }
+            }
+            "map_Kd" => {
+                // Options such as `-s` may precede the file name, which is
+                // looked up relative to the material library.
+                let file = match args.last() {
+                    Some(file) => file,
+                    None => return parser.error("missing texture path"),
+                };
+                match ImageTexture::load(dir.join(file)) {
+                    Ok(texture) => material.diffuse_map = Some(texture),
+                    Err(e) => return parser.error(format!("could not load '{}': {}", file, e)),
+                }
+            }
+            // Other texture maps and parameters have no equivalent here.
+            _ => {}
+        }
+        Ok(())
+    })?;
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+    Ok(())
+}
+
+/// Loads the model at `path`, along with the material libraries it refers to
+/// (which are looked up relative to the model's directory), returning a mesh
+/// for each group of faces that share a material.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Vec<ObjGroup>, ObjError> {
+    let meshes = parse_obj(path.as_ref())?;
+    Ok(meshes.into_iter().map(MeshBuilder::build).collect())
+}
+
+/// Parses the model at `path` into the meshes of its groups, which are yet to
+/// be built.
+fn parse_obj(path: &Path) -> Result<Vec<MeshBuilder>, ObjError> {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut materials = HashMap::new();
+    let mut groups = Vec::new();
+    let mut mesh = MeshBuilder::new(String::from("default"), MtlMaterial::default());
+
+    for_each_statement(path, |parser, keyword, args| {
+        match keyword {
+            "v" => positions.push(parser.vec3(args)?),
+            "vn" => {
+                let normal = parser.vec3(args)?;
+                if normal.squared_len() == 0.0 {
+                    return parser.error("normal must not be zero");
+                }
+                normals.push(normal.to_unit());
+            }
+            "vt" => {
+                // The third, depth coordinate is optional and unused.
+                let uv = parser.floats(args, 1)?;
+                let v = if args.len() > 1 { parser.floats(&args[1..], 1)?[0] } else { 0.0 };
+                uvs.push([uv[0], v]);
+            }
+            "f" => {
+                if args.len() < 3 {
+                    let message = format!("face needs at least 3 vertices, got {}", args.len());
+                    return parser.error(message);
+                }
+                let mut face = Vec::with_capacity(args.len());
+                for arg in args {
+                    // Each vertex is one of `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+                    let mut refs = arg.split('/');
+                    let p = parser.index(refs.next().unwrap_or(""), positions.len(), "vertex")?;
+                    let t = match refs.next() {
+                        Some("") | None => None,
+                        Some(t) => Some(parser.index(t, uvs.len(), "texture coordinate")?),
+                    };
+                    let n = match refs.next() {
+                        Some("") | None => None,
+                        Some(n) => Some(parser.index(n, normals.len(), "normal")?),
+                    };
+                    if refs.next().is_some() {
+                        return parser.error(format!("invalid face vertex '{}'", arg));
+                    }
+
+                    let next_index = mesh.positions.len();
+                    let index = *mesh.vertex_indices.entry((p, t, n)).or_insert(next_index);
+                    if index == next_index {
+                        mesh.positions.push(positions[p]);
+                        mesh.uvs.push(t.map(|t| uvs[t]));
+                        mesh.normals.push(n.map(|n| normals[n]));
+                    }
+                    face.push(index);
+                }
+                // Triangulate the polygon as a fan around its first vertex.
+                for i in 1..face.len() - 1 {
+                    mesh.indices.push([face[0], face[i], face[i + 1]]);
+                }
+            }
+            "g" | "o" | "usemtl" => {
+                let (name, material) = if keyword == "usemtl" {
+                    let name = args.join(" ");
+                    match materials.get(&name) {
+                        Some(material) => (mesh.name.clone(), MtlMaterial::clone(material)),
+                        None => return parser.error(format!("unknown material '{}'", name)),
+                    }
+                } else {
+                    (args.join(" "), mesh.material.clone())
+                };
+                let next = MeshBuilder::new(name, material);
+                let prev = std::mem::replace(&mut mesh, next);
+                if !prev.indices.is_empty() {
+                    groups.push(prev);
+                }
+            }
+            "mtllib" => {
+                if args.is_empty() {
+                    return parser.error("missing material library path");
+                }
+                for lib in args {
+                    load_mtl(&dir.join(lib), &mut materials)?;
+                }
+            }
+            // Smoothing groups, free-form geometry and other statements are
+            // not supported.
+            _ => {}
+        }
+        Ok(())
+    })?;
+
+    if !mesh.indices.is_empty() {
+        groups.push(mesh);
+    }
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use raytracer::hit::HitRecord;
+    use raytracer::ray::Ray;
+    use raytracer::sampler::Sampler;
+    use raytracer::vec3::dot;
+
+    /// A directory of model files for a single test, removed once it is done.
+    struct ObjDir(PathBuf);
+
+    impl ObjDir {
+        fn new(test: &str) -> Self {
+            let dir = env::temp_dir().join(format!("raytracer-obj-{}-{}", test, process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            ObjDir(dir)
+        }
+
+        /// Writes `contents` to the file at the relative `path`, returning its
+        /// full path.
+        fn write(&self, path: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for ObjDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Parses `contents` as an OBJ file, expecting it to fail, and returns the
+    /// line and message of the error.
+    fn parse_error(name: &str, contents: &str) -> (usize, String) {
+        let dir = ObjDir::new(name);
+        match parse_obj(&dir.write(name, contents)) {
+            Err(ObjError::Parse { line, message, .. }) => (line, message),
+            Err(e) => panic!("expected a parse error, got {}", e),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn polygons_are_triangulated_as_fans() {
+        let dir = ObjDir::new("fan");
+        let path = dir.write(
+            "fan.obj",
+            "v 0 0 0\nv 1 0 0\nv 2 1 0\nv 1 2 0\nv 0 1 0\nf 1 2 3 4 5\nf 1 2 3\n",
+        );
+        let meshes = parse_obj(&path).unwrap();
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].positions.len(), 5);
+        assert_eq!(meshes[0].indices, vec![[0, 1, 2], [0, 2, 3], [0, 3, 4], [0, 1, 2]]);
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_element() {
+        let dir = ObjDir::new("relative");
+        let path = dir.write(
+            "relative.obj",
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 2\nf -3//-1 -2//-1 -1//1\n\
+             v 5 5 5\nf 1 -1 -2\n",
+        );
+        let meshes = parse_obj(&path).unwrap();
+        let mesh = &meshes[0];
+        // The first face's vertices have normals, so they are not shared with
+        // the second one's.
+        let z = Some(Vec3 { x: 0.0, y: 0.0, z: 1.0 });
+        assert_eq!(mesh.normals, vec![z, z, z, None, None, None]);
+        let positions: Vec<Vec3> = mesh.indices[1].iter().map(|&i| mesh.positions[i]).collect();
+        assert_eq!(positions[0], Vec3 { x: 0.0, y: 0.0, z: 0.0 });
+        assert_eq!(positions[1], Vec3 { x: 5.0, y: 5.0, z: 5.0 });
+        assert_eq!(positions[2], Vec3 { x: 0.0, y: 1.0, z: 0.0 });
+    }
+
+    #[test]
+    fn out_of_range_indices_are_reported_at_their_line() {
+        let (line, message) = parse_error(
+            "range.obj",
+            "# A triangle missing a vertex.\nv 0 0 0\nv 1 0 0\n\nf 1 2 3\n",
+        );
+        assert_eq!(line, 5);
+        assert_eq!(message, "vertex index 3 out of range");
+        let (line, message) = parse_error("zero.obj", "v 0 0 0\nf 0 1 1\n");
+        assert_eq!(line, 2);
+        assert_eq!(message, "vertex index 0 out of range");
+    }
+
+    #[test]
+    fn unknown_materials_are_reported_at_their_line() {
+        let (line, message) = parse_error("material.obj", "v 0 0 0\nusemtl missing\n");
+        assert_eq!(line, 2);
+        assert_eq!(message, "unknown material 'missing'");
+    }
+
+    #[test]
+    fn zero_normals_are_rejected() {
+        let (line, message) = parse_error("normal.obj", "vn 0 0 1\nvn 0 0 0\n");
+        assert_eq!(line, 2);
+        assert_eq!(message, "normal must not be zero");
+    }
+
+    /// The renderer's material that an MTL material was mapped to, told apart
+    /// by how it scatters light arriving at 45 degrees onto a surface facing
+    /// +z.
+    #[derive(Debug, PartialEq)]
+    enum Kind {
+        Lambertian { albedo: Vec3 },
+        /// The fuzz is estimated as the furthest the reflections stray from
+        /// the mirror direction, and rounded to a tenth.
+        Reflective { albedo: Vec3, fuzz: f32 },
+        Dielectric,
+    }
+
+    fn kind(material: &MtlMaterial) -> Kind {
+        let material = material.to_scatter();
+        let normal = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+        let hit = HitRecord {
+            t: 1.0,
+            point: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+            normal,
+            front_face: true,
+            u: 0.5,
+            v: 0.5,
+            material: &*material,
+        };
+        let ray = Ray {
+            origin: Vec3 { x: -1.0, y: 0.0, z: 1.0 },
+            direction: Vec3 { x: 1.0, y: 0.0, z: -1.0 },
+            time: 0.0,
+        };
+        let mirror = Vec3 { x: 1.0, y: 0.0, z: 1.0 }.to_unit();
+
+        let mut sampler = Sampler::new(3);
+        let mut fuzz: f32 = 0.0;
+        let mut record = None;
+        for _ in 0..2000 {
+            let scattered = match material.scatter(&ray, &hit, &mut sampler) {
+                Some(scattered) => scattered,
+                None => continue,
+            };
+            let direction = scattered.ray.direction;
+            if dot(direction, normal) < 0.0 {
+                return Kind::Dielectric;
+            }
+            fuzz = fuzz.max((direction - mirror).len());
+            record = Some(scattered);
+        }
+        let record = record.unwrap();
+        if record.is_specular {
+            let fuzz = (fuzz * 10.0).round() / 10.0;
+            Kind::Reflective { albedo: record.attenuation, fuzz }
+        } else {
+            Kind::Lambertian { albedo: record.attenuation }
+        }
+    }
+
+    /// Loads the MTL material library `contents`.
+    fn load_materials(name: &str, contents: &str) -> HashMap<String, MtlMaterial> {
+        let dir = ObjDir::new(name);
+        let mut materials = HashMap::new();
+        load_mtl(&dir.write(name, contents), &mut materials).unwrap();
+        materials
+    }
+
+    fn color(x: f32, y: f32, z: f32) -> Vec3 {
+        Vec3 { x, y, z }
+    }
+
+    #[test]
+    fn illumination_models_pick_the_material() {
+        let materials = load_materials(
+            "illum.mtl",
+            "newmtl default\nKd 0.5 0.25 0.125\n\
+             newmtl matte\nKd 0.5 0.25 0.125\nKs 0.5 0.5 0.5\nillum 2\n\
+             newmtl specular\nKd 0.1 0.1 0.1\nKs 0.5 0.5 0.5\nNs 1000\nillum 2\n\
+             newmtl mirror3\nKd 0.9 0.9 0.9\nKs 0.25 0.5 0.75\nNs 1000\nillum 3\n\
+             newmtl mirror5\nKs 0.25 0.5 0.75\nNs 1000\nillum 5\n\
+             newmtl mirror8\nKs 0.25 0.5 0.75\nNs 1000\nillum 8\n\
+             newmtl glass4\nillum 4\nnewmtl glass6\nillum 6\n\
+             newmtl glass7\nillum 7\nnewmtl glass9\nillum 9\n",
+        );
+        let diffuse = Kind::Lambertian { albedo: color(0.5, 0.25, 0.125) };
+        assert_eq!(kind(&materials["default"]), diffuse);
+        // Specular colors brighter than the diffuse one make materials metals.
+        assert_eq!(kind(&materials["matte"]), diffuse);
+        let specular = Kind::Reflective { albedo: color(0.5, 0.5, 0.5), fuzz: 0.0 };
+        assert_eq!(kind(&materials["specular"]), specular);
+        let mirror = Kind::Reflective { albedo: color(0.25, 0.5, 0.75), fuzz: 0.0 };
+        for name in ["mirror3", "mirror5", "mirror8"].iter() {
+            assert_eq!(kind(&materials[*name]), mirror, "{}", name);
+        }
+        for name in ["glass4", "glass6", "glass7", "glass9"].iter() {
+            assert_eq!(kind(&materials[*name]), Kind::Dielectric, "{}", name);
+        }
+    }
+
+    #[test]
+    fn dissolved_materials_are_transparent() {
+        let materials = load_materials(
+            "dissolve.mtl",
+            "newmtl d\nd 0.5\nnewmtl tr\nTr 0.25\n\
+             newmtl opaque_d\nd 1\nnewmtl opaque_tr\nTr 0\n\
+             newmtl glass\nd 0.9\nNi 1.33\n",
+        );
+        assert_eq!(materials["tr"].dissolve, 0.75);
+        assert_eq!(kind(&materials["d"]), Kind::Dielectric);
+        assert_eq!(kind(&materials["tr"]), Kind::Dielectric);
+        let opaque = Kind::Lambertian { albedo: color(0.8, 0.8, 0.8) };
+        assert_eq!(kind(&materials["opaque_d"]), opaque);
+        assert_eq!(kind(&materials["opaque_tr"]), opaque);
+        assert_eq!(materials["glass"].optical_density, 1.33);
+        assert_eq!(kind(&materials["glass"]), Kind::Dielectric);
+    }
+
+    #[test]
+    fn shininess_sets_the_fuzz() {
+        let materials = load_materials(
+            "shininess.mtl",
+            "newmtl ns0\nKs 1 1 1\nillum 3\n\
+             newmtl ns250\nKs 1 1 1\nNs 250\nillum 3\n\
+             newmtl ns1000\nKs 1 1 1\nNs 1000\nillum 3\n\
+             newmtl ns5000\nKs 1 1 1\nNs 5000\nillum 3\n",
+        );
+        let albedo = color(1.0, 1.0, 1.0);
+        let fuzzes = [("ns0", 1.0), ("ns250", 0.5), ("ns1000", 0.0), ("ns5000", 0.0)];
+        for &(name, fuzz) in fuzzes.iter() {
+            assert_eq!(kind(&materials[name]), Kind::Reflective { albedo, fuzz }, "{}", name);
+        }
+    }
+
+    #[test]
+    fn diffuse_maps_are_found_next_to_their_library() {
+        let dir = ObjDir::new("map");
+        let texture = dir.0.join("materials/textures/red.png");
+        fs::create_dir_all(texture.parent().unwrap()).unwrap();
+        image::RgbImage::from_pixel(2, 2, image::Rgb([255, 0, 0])).save(&texture).unwrap();
+        dir.write(
+            "materials/textured.mtl",
+            "newmtl red\nKd 0.5 0.5 0.5\nmap_Kd -s 1 1 1 textures/red.png\n",
+        );
+        let path = dir.write(
+            "model.obj",
+            "mtllib materials/textured.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n",
+        );
+        let meshes = parse_obj(&path).unwrap();
+        assert_eq!(kind(&meshes[0].material), Kind::Lambertian { albedo: color(1.0, 0.0, 0.0) });
+
+        // Paths are not resolved relative to the model.
+        let path = dir.write("broken.mtl", "newmtl red\nmap_Kd textures/red.png\n");
+        let mut materials = HashMap::new();
+        match load_mtl(&path, &mut materials) {
+            Err(ObjError::Parse { line: 2, .. }) => {}
+            Err(e) => panic!("expected a parse error on line 2, got {}", e),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+}
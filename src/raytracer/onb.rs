@@ -0,0 +1,30 @@
+use raytracer::vec3::*;
+
+/// An orthonormal basis, used to transform directions sampled around the z
+/// axis to directions around an arbitrary axis `w`.
+#[derive(Debug, Copy, Clone)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// Builds a basis around the unit vector `w`, using the branchless method
+    /// of Duff et al., "Building an Orthonormal Basis, Revisited".
+    pub fn from_w(w: Vec3) -> Self {
+        let sign = 1.0f32.copysign(w.z);
+        let a = -1.0 / (sign + w.z);
+        let b = w.x * w.y * a;
+        Self {
+            u: Vec3 { x: 1.0 + sign * w.x * w.x * a, y: sign * b, z: -sign * w.x },
+            v: Vec3 { x: b, y: sign + w.y * w.y * a, z: -w.y },
+            w,
+        }
+    }
+
+    /// Transforms `a`, given in this basis' coordinates, to world space.
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+}
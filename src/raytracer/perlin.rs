@@ -0,0 +1,104 @@
+use raytracer::sampler::Sampler;
+use raytracer::vec3::*;
+
+/// The number of gradients, after which the noise repeats along each axis.
+const POINT_COUNT: usize = 256;
+
+/// Ken Perlin's gradient noise: a random unit vector is assigned to every
+/// point of the integer lattice, and the noise at a point is the trilinear
+/// interpolation of the dot products of the gradients at the eight lattice
+/// points around it with the offsets from them. Interpolating with Hermite
+/// smoothing rather than linearly avoids visible grid artifacts.
+#[derive(Clone)]
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    /// Permutations of the gradient indices for each axis, which are combined
+    /// to hash a lattice point into a gradient.
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    /// Creates the noise with gradients and permutations drawn from `seed`,
+    /// so the same seed always gives the same noise.
+    pub fn new(seed: u64) -> Self {
+        let mut sampler = Sampler::new(seed);
+        let gradients = (0..POINT_COUNT).map(|_| sampler.unit_vector()).collect();
+        let perm_x = permutation(&mut sampler);
+        let perm_y = permutation(&mut sampler);
+        let perm_z = permutation(&mut sampler);
+        Self {
+            gradients,
+            perm_x,
+            perm_y,
+            perm_z,
+        }
+    }
+
+    /// Returns the noise at `p`, which lies in the range -1 to 1 and is zero at
+    /// every lattice point.
+    pub fn noise(&self, p: Vec3) -> f32 {
+        let (x, y, z) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (i, j, k) = (x as i64, y as i64, z as i64);
+        let offset = Vec3 { x: p.x - x, y: p.y - y, z: p.z - z };
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let (u, v, w) = (smooth(offset.x), smooth(offset.y), smooth(offset.z));
+
+        let mut sum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let index = self.perm_x[wrap(i + di)]
+                        ^ self.perm_y[wrap(j + dj)]
+                        ^ self.perm_z[wrap(k + dk)];
+                    let corner = Vec3 { x: di as f32, y: dj as f32, z: dk as f32 };
+                    let weight = lerp_weight(u, di) * lerp_weight(v, dj) * lerp_weight(w, dk);
+                    sum += weight * dot(self.gradients[index], offset - corner);
+                }
+            }
+        }
+        sum
+    }
+
+    /// Sums the absolute noise of `octaves` octaves, each of twice the
+    /// frequency and half the amplitude of the previous one, which gives the
+    /// noise detail at every scale.
+    pub fn turbulence(&self, p: Vec3, octaves: usize) -> f32 {
+        let mut sum = 0.0;
+        let mut p = p;
+        let mut amplitude = 1.0;
+        for _ in 0..octaves {
+            sum += amplitude * self.noise(p).abs();
+            p *= 2.0;
+            amplitude *= 0.5;
+        }
+        sum
+    }
+}
+
+/// Returns a random permutation of the gradient indices.
+fn permutation(sampler: &mut Sampler) -> Vec<usize> {
+    let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+    // Fisher-Yates shuffle.
+    for i in (1..POINT_COUNT).rev() {
+        let j = ((sampler.next_f32() * (i + 1) as f32) as usize).min(i);
+        perm.swap(i, j);
+    }
+    perm
+}
+
+/// Maps a lattice coordinate, which may be negative, onto a permutation index.
+fn wrap(i: i64) -> usize {
+    (i & (POINT_COUNT as i64 - 1)) as usize
+}
+
+/// The weight of the lattice point at offset `d`, 0 or 1, when interpolating
+/// at `t`.
+fn lerp_weight(t: f32, d: i64) -> f32 {
+    if d == 0 {
+        1.0 - t
+    } else {
+        t
+    }
+}
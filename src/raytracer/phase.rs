@@ -0,0 +1,163 @@
+//! Phase functions, which describe in which directions light scatters within
+//! a medium, as BSDFs do for surfaces.
+//!
+//! They are used by the `Volume` material, which gives a medium its albedo,
+//! and are functions of the angle between the direction in which light was
+//! travelling and the one in which it scatters: unlike surfaces, media have no
+//! normal, and no cosine term weights their scattering.
+
+use std::f32::consts::PI;
+
+use raytracer::onb::Onb;
+use raytracer::sampler::Sampler;
+use raytracer::vec3::*;
+
+/// Like `Scatter`, phase functions are shared between render threads. All the
+/// directions they are given and return are unit vectors, with `incoming`
+/// being the direction in which light travels towards the point at which it
+/// scatters.
+pub trait PhaseFunction: Send + Sync {
+    /// Returns a direction in which light arriving along `incoming` scatters,
+    /// sampled with the density given by `pdf`.
+    fn sample(&self, incoming: Vec3, sampler: &mut Sampler) -> Vec3;
+
+    /// The density, per unit solid angle, of light arriving along `incoming`
+    /// scattering towards `scattered`. It integrates to 1 over the sphere of
+    /// directions, as the medium's albedo accounts for the light it absorbs.
+    fn eval(&self, incoming: Vec3, scattered: Vec3) -> f32;
+
+    /// The probability density, with respect to solid angle, of `sample`
+    /// returning `scattered`, which by default is the phase function itself,
+    /// as for those sampled exactly.
+    fn pdf(&self, incoming: Vec3, scattered: Vec3) -> f32 {
+        self.eval(incoming, scattered)
+    }
+}
+
+/// Scatters light evenly in all directions, independent of where it came from.
+#[derive(Debug, Copy, Clone)]
+pub struct Isotropic;
+
+impl PhaseFunction for Isotropic {
+    fn sample(&self, _incoming: Vec3, sampler: &mut Sampler) -> Vec3 {
+        sampler.unit_vector()
+    }
+
+    fn eval(&self, _incoming: Vec3, _scattered: Vec3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+/// The Henyey-Greenstein phase function, which models media scattering more
+/// light forwards or backwards with a single parameter `g`, the average cosine
+/// of the angle by which light is deflected. It lies strictly between -1 and 1:
+/// positive values scatter light forwards, as do clouds, fog and skin, negative
+/// ones back towards where it came from, and zero evenly, like `Isotropic`.
+#[derive(Debug, Copy, Clone)]
+pub struct HenyeyGreenstein {
+    pub g: f32,
+}
+
+impl PhaseFunction for HenyeyGreenstein {
+    fn sample(&self, incoming: Vec3, sampler: &mut Sampler) -> Vec3 {
+        // Invert the distribution of the cosine of the angle of deflection,
+        // whose formula divides by `g`, and so is replaced by the uniform
+        // distribution of the isotropic case when it nears zero.
+        let g = self.g;
+        let xi = sampler.next_f32();
+        let cos = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let phi = 2.0 * PI * sampler.next_f32();
+        let local = Vec3 { x: sin * phi.cos(), y: sin * phi.sin(), z: cos };
+        Onb::from_w(incoming).local(local)
+    }
+
+    fn eval(&self, incoming: Vec3, scattered: Vec3) -> f32 {
+        let g = self.g;
+        let cos = dot(incoming, scattered);
+        let denom = 1.0 + g * g - 2.0 * g * cos;
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Values of `g` spanning backward and forward scattering, including ones
+    /// small enough to be sampled as if isotropic.
+    const GS: [f32; 7] = [-0.9, -0.4, -5e-4, 0.0, 5e-4, 0.3, 0.8];
+
+    fn incoming() -> Vec3 {
+        Vec3 { x: 0.3, y: -0.5, z: 0.8 }.to_unit()
+    }
+
+    #[test]
+    fn henyey_greenstein_integrates_to_one() {
+        // The phase function only depends on the angle of deflection, so its
+        // integral over the sphere is one over the cosine of that angle,
+        // which is taken by the midpoint rule.
+        let incoming = incoming();
+        let basis = Onb::from_w(incoming);
+        for &g in GS.iter() {
+            let phase = HenyeyGreenstein { g };
+            let n = 100_000;
+            let integral: f64 = (0..n)
+                .map(|i| {
+                    let cos = -1.0 + (2.0 * i as f32 + 1.0) / n as f32;
+                    let sin = (1.0 - cos * cos).sqrt();
+                    let scattered = basis.local(Vec3 { x: sin, y: 0.0, z: cos });
+                    (2.0 * PI * phase.eval(incoming, scattered)) as f64 * 2.0 / n as f64
+                })
+                .sum();
+            assert!((integral - 1.0).abs() < 1e-3, "g = {}: integral {}", g, integral);
+        }
+    }
+
+    #[test]
+    fn henyey_greenstein_samples_have_mean_cosine_g() {
+        let incoming = incoming();
+        let mut sampler = Sampler::new(7);
+        for &g in GS.iter() {
+            let phase = HenyeyGreenstein { g };
+            let n = 200_000;
+            let mut sum = 0.0;
+            for _ in 0..n {
+                let scattered = phase.sample(incoming, &mut sampler);
+                assert!((scattered.len() - 1.0).abs() < 1e-4);
+                sum += dot(incoming, scattered) as f64;
+            }
+            let mean = sum / n as f64;
+            assert!((mean - g as f64).abs() < 5e-3, "g = {}: mean cosine {}", g, mean);
+        }
+    }
+
+    #[test]
+    fn henyey_greenstein_samples_follow_its_density() {
+        // The fraction of samples deflected by less than 90 degrees matches
+        // the integral of the phase function over the forward hemisphere.
+        let incoming = incoming();
+        let basis = Onb::from_w(incoming);
+        let phase = HenyeyGreenstein { g: 0.6 };
+        let n = 100_000;
+        let expected: f64 = (0..n)
+            .map(|i| {
+                let cos = (i as f32 + 0.5) / n as f32;
+                let sin = (1.0 - cos * cos).sqrt();
+                let scattered = basis.local(Vec3 { x: sin, y: 0.0, z: cos });
+                (2.0 * PI * phase.eval(incoming, scattered)) as f64 / n as f64
+            })
+            .sum();
+        let mut sampler = Sampler::new(11);
+        let forward = (0..n)
+            .filter(|_| dot(incoming, phase.sample(incoming, &mut sampler)) > 0.0)
+            .count();
+        let fraction = forward as f64 / n as f64;
+        assert!((fraction - expected).abs() < 5e-3, "{} != {}", fraction, expected);
+    }
+}
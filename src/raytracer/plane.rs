@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use raytracer::aabb::Aabb;
+use raytracer::hit::*;
+use raytracer::onb::Onb;
+use raytracer::ray::*;
+use raytracer::scatter::*;
+use raytracer::vec3::*;
+
+/// An infinite plane through `point`, seen from both sides, with `normal`
+/// pointing out of its outside.
+///
+/// Its surface coordinates are the distances from `point` along two directions
+/// perpendicular to the normal, so image textures repeat once every unit. Being
+/// unbounded, it is kept outside of the bounding volume hierarchies and tested
+/// against every ray, and it is never sampled as a light.
+pub struct Plane {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub material: Arc<dyn Scatter>,
+}
+
+impl Hit for Plane {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        let normal = self.normal.to_unit();
+        // Rays parallel to the plane give an infinite or NaN offset, which
+        // fails the comparison below.
+        let t = dot(self.point - ray.origin, normal) / dot(ray.direction, normal);
+        if !(t > min && t < max) {
+            return None;
+        }
+        let point = ray.point_at(t);
+        let basis = Onb::from_w(normal);
+        let (normal, front_face) = face_normal(ray, normal);
+        Some(HitRecord {
+            t,
+            point,
+            normal,
+            front_face,
+            u: dot(point - self.point, basis.u),
+            v: dot(point - self.point, basis.v),
+            material: &*self.material,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
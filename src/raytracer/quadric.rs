@@ -0,0 +1,374 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use raytracer::aabb::Aabb;
+use raytracer::hit::*;
+use raytracer::light::Light;
+use raytracer::onb::Onb;
+use raytracer::ray::*;
+use raytracer::roots::solve_quadratic;
+use raytracer::sampler::Sampler;
+use raytracer::scatter::*;
+use raytracer::vec3::*;
+
+/// Disks are flat, so their bounding boxes are padded by this much to still be
+/// hit by rays.
+const BBOX_PADDING: f32 = 1e-4;
+
+const UP: Vec3 = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
+const DOWN: Vec3 = Vec3 { x: 0.0, y: -1.0, z: 0.0 };
+
+/// A cylinder standing upright on the circle around `center` in the y = 0
+/// plane of its own, rising `height` above it. Its surface coordinates run
+/// around its axis and up along it.
+///
+/// Unless it is `capped` by disks at both ends, it is an open tube, whose
+/// inside is seen through them. Other orientations are had by transforming it.
+pub struct Cylinder {
+    pub center: Vec3,
+    pub radius: f32,
+    pub height: f32,
+    pub capped: bool,
+    pub material: Arc<dyn Scatter>,
+}
+
+impl Hit for Cylinder {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        // (x - cx)^2 + (z - cz)^2 = r^2 for the points along the ray.
+        let o = ray.origin - self.center;
+        let d = ray.direction;
+        let a = d.x * d.x + d.z * d.z;
+        let b = 2.0 * (o.x * d.x + o.z * d.z);
+        let c = o.x * o.x + o.z * o.z - self.radius * self.radius;
+        let side = |t: f32| {
+            let local = o + t * d;
+            if local.y < 0.0 || local.y > self.height {
+                return None;
+            }
+            let outward = Vec3 { x: local.x, y: 0.0, z: local.z } / self.radius;
+            let (normal, front_face) = face_normal(ray, outward);
+            Some(HitRecord {
+                t,
+                point: ray.point_at(t),
+                normal,
+                front_face,
+                u: azimuth(local),
+                v: local.y / self.height,
+                material: &*self.material,
+            })
+        };
+        let mut record = nearest_root(a, b, c, min, max, side);
+        if self.capped {
+            let mut closest = record.as_ref().map_or(max, |rec| rec.t);
+            for &(offset, normal) in [(0.0, DOWN), (self.height, UP)].iter() {
+                let cap = DiskShape {
+                    center: self.center + offset * UP,
+                    normal,
+                    inner_radius: 0.0,
+                    outer_radius: self.radius,
+                };
+                if let Some(rec) = cap.hit(&*self.material, ray, min, closest) {
+                    closest = rec.t;
+                    record = Some(rec);
+                }
+            }
+        }
+        record
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3 { x: self.radius, y: 0.0, z: self.radius };
+        Some(Aabb {
+            min: self.center - r,
+            max: self.center + r + self.height * UP,
+        })
+    }
+}
+
+/// A cone standing upright on the circle around `center` in the y = 0 plane of
+/// its own, narrowing to its apex `height` above it. Its surface coordinates
+/// run around its axis and up along it.
+///
+/// Unless it is `capped` by a disk at its base, it is open, and its inside is
+/// seen through the base. Other orientations are had by transforming it.
+pub struct Cone {
+    pub center: Vec3,
+    pub radius: f32,
+    pub height: f32,
+    pub capped: bool,
+    pub material: Arc<dyn Scatter>,
+}
+
+impl Hit for Cone {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        // (x - cx)^2 + (z - cz)^2 = (k * (h - y))^2 for the points along the
+        // ray, where k is the ratio of the radius to the height. Measuring
+        // `y` down from the apex, as `h - y`, keeps the equation simple.
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let o = ray.origin - self.center;
+        let d = ray.direction;
+        let (oy, dy) = (self.height - o.y, -d.y);
+        let a = d.x * d.x + d.z * d.z - k2 * dy * dy;
+        let b = 2.0 * (o.x * d.x + o.z * d.z - k2 * oy * dy);
+        let c = o.x * o.x + o.z * o.z - k2 * oy * oy;
+        let side = |t: f32| {
+            let local = o + t * d;
+            // The equation also describes the mirrored cone above the apex.
+            if local.y < 0.0 || local.y > self.height {
+                return None;
+            }
+            // The gradient of the equation, which vanishes at the apex.
+            let gradient = Vec3 { x: local.x, y: k2 * (self.height - local.y), z: local.z };
+            let outward = if gradient.squared_len() > 0.0 { gradient.to_unit() } else { UP };
+            let (normal, front_face) = face_normal(ray, outward);
+            Some(HitRecord {
+                t,
+                point: ray.point_at(t),
+                normal,
+                front_face,
+                u: azimuth(local),
+                v: local.y / self.height,
+                material: &*self.material,
+            })
+        };
+        let mut record = nearest_root(a, b, c, min, max, side);
+        if self.capped {
+            let closest = record.as_ref().map_or(max, |rec| rec.t);
+            let base = DiskShape {
+                center: self.center,
+                normal: DOWN,
+                inner_radius: 0.0,
+                outer_radius: self.radius,
+            };
+            if let Some(rec) = base.hit(&*self.material, ray, min, closest) {
+                record = Some(rec);
+            }
+        }
+        record
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3 { x: self.radius, y: 0.0, z: self.radius };
+        Some(Aabb {
+            min: self.center - r,
+            max: self.center + r + self.height * UP,
+        })
+    }
+}
+
+/// A flat disk around `center`, seen from both sides, with `normal` pointing
+/// out of its outside. Its surface coordinates are the angle around the center
+/// and the distance from it, as a fraction of the radius.
+pub struct Disk {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub radius: f32,
+    pub material: Arc<dyn Scatter>,
+}
+
+impl Disk {
+    fn shape(&self) -> DiskShape {
+        DiskShape {
+            center: self.center,
+            normal: self.normal.to_unit(),
+            inner_radius: 0.0,
+            outer_radius: self.radius,
+        }
+    }
+}
+
+impl Hit for Disk {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        self.shape().hit(&*self.material, ray, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.shape().bounding_box())
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        if self.material.is_emissive() {
+            lights.push(Box::new(self.shape()));
+        }
+    }
+}
+
+/// A flat ring around `center` between `inner_radius` and `outer_radius`, seen
+/// from both sides, with `normal` pointing out of its outside. Its surface
+/// coordinates are the angle around the center and the fraction of the way
+/// from the inner edge to the outer one.
+pub struct Annulus {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub material: Arc<dyn Scatter>,
+}
+
+impl Annulus {
+    fn shape(&self) -> DiskShape {
+        DiskShape {
+            center: self.center,
+            normal: self.normal.to_unit(),
+            inner_radius: self.inner_radius,
+            outer_radius: self.outer_radius,
+        }
+    }
+}
+
+impl Hit for Annulus {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        self.shape().hit(&*self.material, ray, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.shape().bounding_box())
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        if self.material.is_emissive() {
+            lights.push(Box::new(self.shape()));
+        }
+    }
+}
+
+/// The shape of disks, annuli and the caps of cylinders and cones, which is a
+/// disk when `inner_radius` is zero. Its lights are sampled uniformly over its
+/// area.
+#[derive(Copy, Clone)]
+struct DiskShape {
+    center: Vec3,
+    /// The unit normal.
+    normal: Vec3,
+    inner_radius: f32,
+    outer_radius: f32,
+}
+
+impl DiskShape {
+    /// Returns the offset along `ray` at which it hits the disk, along with
+    /// the distance of the hit from the center.
+    fn intersect(&self, ray: &Ray, min: f32, max: f32) -> Option<(f32, f32)> {
+        // Rays parallel to the disk give an infinite or NaN offset, which
+        // fails the comparison below.
+        let t = dot(self.center - ray.origin, self.normal) / dot(ray.direction, self.normal);
+        if !(t > min && t < max) {
+            return None;
+        }
+        let r = (ray.point_at(t) - self.center).len();
+        if r < self.inner_radius || r > self.outer_radius {
+            return None;
+        }
+        Some((t, r))
+    }
+
+    fn hit<'a>(
+        &self,
+        material: &'a dyn Scatter,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        let (t, r) = self.intersect(ray, min, max)?;
+        let point = ray.point_at(t);
+        let basis = Onb::from_w(self.normal);
+        let offset = point - self.center;
+        let angle = dot(offset, basis.v).atan2(dot(offset, basis.u));
+        let (normal, front_face) = face_normal(ray, self.normal);
+        Some(HitRecord {
+            t,
+            point,
+            normal,
+            front_face,
+            u: angle / (2.0 * PI) + 0.5,
+            v: (r - self.inner_radius) / (self.outer_radius - self.inner_radius),
+            material,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // The disk extends along each axis by its radius times the sine of
+        // the angle between the axis and the normal.
+        let n = self.normal;
+        let extent = |n: f32| self.outer_radius * (1.0 - n * n).max(0.0).sqrt() + BBOX_PADDING;
+        let e = Vec3 { x: extent(n.x), y: extent(n.y), z: extent(n.z) };
+        Aabb {
+            min: self.center - e,
+            max: self.center + e,
+        }
+    }
+}
+
+impl Light for DiskShape {
+    fn sample_direction(&self, origin: Vec3, sampler: &mut Sampler) -> Vec3 {
+        // Taking the square root spreads the points evenly over the area
+        // rather than crowding them at the center.
+        let (r0, r1) = (self.inner_radius, self.outer_radius);
+        let r = (r0 * r0 + sampler.next_f32() * (r1 * r1 - r0 * r0)).sqrt();
+        let angle = 2.0 * PI * sampler.next_f32();
+        let basis = Onb::from_w(self.normal);
+        let point = self.center + r * angle.cos() * basis.u + r * angle.sin() * basis.v;
+        point - origin
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f32 {
+        let ray = Ray { origin, direction: direction.to_unit(), time: 0.0 };
+        match self.intersect(&ray, 0.001, f32::MAX) {
+            Some((t, _)) => {
+                // Convert the density over the disk's area to one over solid
+                // angle.
+                let (r0, r1) = (self.inner_radius, self.outer_radius);
+                let area = PI * (r1 * r1 - r0 * r0);
+                let cos = dot(ray.direction, self.normal).abs();
+                if cos > 0.0 {
+                    t * t / (cos * area)
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        }
+    }
+}
+
+/// Returns the hit at the nearer of the roots of `a*t^2 + b*t + c = 0` within
+/// `min` and `max` that `hit` accepts.
+fn nearest_root<'a, F>(a: f32, b: f32, c: f32, min: f32, max: f32, hit: F) -> Option<HitRecord<'a>>
+where
+    F: Fn(f32) -> Option<HitRecord<'a>>,
+{
+    let roots = solve_quadratic(a as f64, b as f64, c as f64);
+    roots
+        .as_slice()
+        .iter()
+        .map(|&t| t as f32)
+        .filter(|&t| t > min && t < max)
+        .filter_map(hit)
+        .next()
+}
+
+/// Returns the angle of the offset `p` around the y axis, starting from -X like
+/// the surface coordinates of spheres, scaled to range from 0 to 1.
+fn azimuth(p: Vec3) -> f32 {
+    ((-p.z).atan2(p.x) + PI) / (2.0 * PI)
+}
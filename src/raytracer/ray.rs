@@ -4,6 +4,9 @@ use raytracer::vec3::Vec3;
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
+    /// The time at which the ray is cast, within the camera's shutter
+    /// interval, at which moving objects are intersected.
+    pub time: f32,
 }
 
 impl Ray {
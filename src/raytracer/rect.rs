@@ -0,0 +1,316 @@
+use std::sync::Arc;
+
+use raytracer::aabb::Aabb;
+use raytracer::hit::*;
+use raytracer::light::Light;
+use raytracer::ray::*;
+use raytracer::sampler::Sampler;
+use raytracer::scatter::*;
+use raytracer::vec3::*;
+
+/// Rectangles have zero extent along their normal, so their bounding boxes are
+/// padded by this much to still be hit by rays.
+const BBOX_PADDING: f32 = 1e-4;
+
+/// A rectangle in the plane `z = k`, spanning `x0` to `x1` and `y0` to `y1`.
+/// It is seen from both sides, its normal pointing along +z on the outside,
+/// and its surface coordinates run along x and y.
+pub struct XyRect {
+    pub x0: f32,
+    pub x1: f32,
+    pub y0: f32,
+    pub y1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Scatter>,
+}
+
+/// A rectangle in the plane `y = k`, spanning `x0` to `x1` and `z0` to `z1`.
+/// It is seen from both sides, its normal pointing along +y on the outside,
+/// and its surface coordinates run along x and z.
+pub struct XzRect {
+    pub x0: f32,
+    pub x1: f32,
+    pub z0: f32,
+    pub z1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Scatter>,
+}
+
+/// A rectangle in the plane `x = k`, spanning `y0` to `y1` and `z0` to `z1`.
+/// It is seen from both sides, its normal pointing along +x on the outside,
+/// and its surface coordinates run along y and z.
+pub struct YzRect {
+    pub y0: f32,
+    pub y1: f32,
+    pub z0: f32,
+    pub z1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Scatter>,
+}
+
+impl XyRect {
+    fn shape(&self) -> AxisRect {
+        AxisRect {
+            axes: (0, 1, 2),
+            a: (self.x0.min(self.x1), self.x0.max(self.x1)),
+            b: (self.y0.min(self.y1), self.y0.max(self.y1)),
+            k: self.k,
+            flip: false,
+        }
+    }
+}
+
+impl XzRect {
+    fn shape(&self) -> AxisRect {
+        AxisRect {
+            axes: (0, 2, 1),
+            a: (self.x0.min(self.x1), self.x0.max(self.x1)),
+            b: (self.z0.min(self.z1), self.z0.max(self.z1)),
+            k: self.k,
+            flip: false,
+        }
+    }
+}
+
+impl YzRect {
+    fn shape(&self) -> AxisRect {
+        AxisRect {
+            axes: (1, 2, 0),
+            a: (self.y0.min(self.y1), self.y0.max(self.y1)),
+            b: (self.z0.min(self.z1), self.z0.max(self.z1)),
+            k: self.k,
+            flip: false,
+        }
+    }
+}
+
+impl Hit for XyRect {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        self.shape().hit(&*self.material, ray, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.shape().bounding_box())
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        if self.material.is_emissive() {
+            lights.push(Box::new(self.shape()));
+        }
+    }
+}
+
+impl Hit for XzRect {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        self.shape().hit(&*self.material, ray, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.shape().bounding_box())
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        if self.material.is_emissive() {
+            lights.push(Box::new(self.shape()));
+        }
+    }
+}
+
+impl Hit for YzRect {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        self.shape().hit(&*self.material, ray, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.shape().bounding_box())
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        if self.material.is_emissive() {
+            lights.push(Box::new(self.shape()));
+        }
+    }
+}
+
+/// The shape shared by the three kinds of rectangles, described along its
+/// two in-plane axes `a` and `b` and its normal axis, in that order in `axes`,
+/// where 0, 1 and 2 stand for x, y and z. Its lights are sampled uniformly
+/// over its area.
+#[derive(Copy, Clone)]
+struct AxisRect {
+    axes: (usize, usize, usize),
+    /// The smaller and larger bound along the `a` axis.
+    a: (f32, f32),
+    /// The smaller and larger bound along the `b` axis.
+    b: (f32, f32),
+    k: f32,
+    /// Whether the outside of the rectangle faces along the negative normal
+    /// axis, as the faces of a `Cuboid` at its smaller bounds do.
+    flip: bool,
+}
+
+impl AxisRect {
+    /// Returns the point at `a` and `b` along the in-plane axes and `k` along
+    /// the normal.
+    fn point(&self, a: f32, b: f32, k: f32) -> Vec3 {
+        let mut c = [0.0; 3];
+        c[self.axes.0] = a;
+        c[self.axes.1] = b;
+        c[self.axes.2] = k;
+        Vec3 { x: c[0], y: c[1], z: c[2] }
+    }
+
+    fn area(&self) -> f32 {
+        (self.a.1 - self.a.0) * (self.b.1 - self.b.0)
+    }
+
+    /// Returns the offset along `ray` at which it hits the rectangle, along
+    /// with the hit's coordinates along the `a` and `b` axes.
+    fn intersect(&self, ray: &Ray, min: f32, max: f32) -> Option<(f32, f32, f32)> {
+        let (a_axis, b_axis, k_axis) = self.axes;
+        // Rays parallel to the plane give an infinite or NaN offset, which
+        // fails the comparisons below.
+        let t = (self.k - ray.origin[k_axis]) / ray.direction[k_axis];
+        if !(t > min && t < max) {
+            return None;
+        }
+        let a = ray.origin[a_axis] + t * ray.direction[a_axis];
+        let b = ray.origin[b_axis] + t * ray.direction[b_axis];
+        if a < self.a.0 || a > self.a.1 || b < self.b.0 || b > self.b.1 {
+            return None;
+        }
+        Some((t, a, b))
+    }
+
+    fn hit<'a>(
+        &self,
+        material: &'a dyn Scatter,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        let (t, a, b) = self.intersect(ray, min, max)?;
+        let sign = if self.flip { -1.0 } else { 1.0 };
+        let (normal, front_face) = face_normal(ray, self.point(0.0, 0.0, sign));
+        Some(HitRecord {
+            t,
+            point: ray.point_at(t),
+            normal,
+            front_face,
+            u: (a - self.a.0) / (self.a.1 - self.a.0),
+            v: (b - self.b.0) / (self.b.1 - self.b.0),
+            material,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb {
+            min: self.point(self.a.0, self.b.0, self.k - BBOX_PADDING),
+            max: self.point(self.a.1, self.b.1, self.k + BBOX_PADDING),
+        }
+    }
+}
+
+impl Light for AxisRect {
+    fn sample_direction(&self, origin: Vec3, sampler: &mut Sampler) -> Vec3 {
+        let a = self.a.0 + sampler.next_f32() * (self.a.1 - self.a.0);
+        let b = self.b.0 + sampler.next_f32() * (self.b.1 - self.b.0);
+        self.point(a, b, self.k) - origin
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f32 {
+        let ray = Ray { origin, direction: direction.to_unit(), time: 0.0 };
+        match self.intersect(&ray, 0.001, f32::MAX) {
+            Some((t, _, _)) => {
+                // Convert the density over the rectangle's area to one over
+                // solid angle.
+                let cos = ray.direction[self.axes.2].abs();
+                if cos > 0.0 {
+                    t * t / (cos * self.area())
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        }
+    }
+}
+
+/// An axis-aligned box, made of six rectangles with their normals pointing out
+/// of it.
+pub struct Cuboid {
+    bbox: Aabb,
+    faces: [AxisRect; 6],
+    material: Arc<dyn Scatter>,
+}
+
+impl Cuboid {
+    /// Creates the box between the opposite corners `a` and `b`.
+    pub fn new(a: Vec3, b: Vec3, material: Arc<dyn Scatter>) -> Self {
+        let bbox = Aabb::surrounding(Aabb { min: a, max: a }, Aabb { min: b, max: b });
+        let (p0, p1) = (bbox.min, bbox.max);
+        // The faces at the smaller bounds are flipped to face out of the box.
+        let face = |axes: (usize, usize, usize), k: f32, flip: bool| AxisRect {
+            axes,
+            a: (p0[axes.0], p1[axes.0]),
+            b: (p0[axes.1], p1[axes.1]),
+            k,
+            flip,
+        };
+        let faces = [
+            face((0, 1, 2), p1.z, false),
+            face((0, 1, 2), p0.z, true),
+            face((0, 2, 1), p1.y, false),
+            face((0, 2, 1), p0.y, true),
+            face((1, 2, 0), p1.x, false),
+            face((1, 2, 0), p0.x, true),
+        ];
+        Self { bbox, faces, material }
+    }
+}
+
+impl Hit for Cuboid {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        let mut record = None;
+        let mut closest = max;
+        for face in self.faces.iter() {
+            if let Some(rec) = face.hit(&*self.material, ray, min, closest) {
+                closest = rec.t;
+                record = Some(rec);
+            }
+        }
+        record
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bbox)
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        if self.material.is_emissive() {
+            for face in self.faces.iter() {
+                lights.push(Box::new(*face));
+            }
+        }
+    }
+}
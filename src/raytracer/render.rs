@@ -0,0 +1,355 @@
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use raytracer::background::{Background, GradientBackground};
+use raytracer::camera::Camera;
+use raytracer::hit::Hit;
+use raytracer::light::LightList;
+use raytracer::ray::Ray;
+use raytracer::rgb::Rgb;
+use raytracer::sampler::Sampler;
+use raytracer::vec3::Vec3;
+
+/// Settings that control the quality and size of the rendered image.
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    /// The number of anti-aliasing samples taken per pixel.
+    #[serde(rename = "samples")]
+    pub n_samples: usize,
+    /// The number of times a ray may bounce before it is considered absorbed.
+    pub max_depth: usize,
+    /// The seed from which all random numbers used in rendering are derived.
+    pub seed: u64,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 600,
+            n_samples: 24,
+            max_depth: 50,
+            seed: 0,
+        }
+    }
+}
+
+/// A rendered image, stored as rows of gamma corrected pixels, top to bottom,
+/// with components in the range 0 to 255.
+#[derive(PartialEq)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Framebuffer {
+    pub fn save_ppm<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create/*_new*/(true)
+            .truncate(true)
+            .open(path)?;
+        let mut file = BufWriter::new(file);
+
+        writeln!(file, "P3")?;
+        writeln!(file, "{} {}", self.width, self.height)?;
+        writeln!(file, "255")?;
+
+        for pixel in self.pixels.iter() {
+            writeln!(file, "{} {} {}", pixel.r as i32, pixel.g as i32, pixel.b as i32)?;
+        }
+        file.flush()
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut img = image::RgbImage::new(self.width as u32, self.height as u32);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = x + y * self.width;
+                let pixel = self.pixels[idx];
+                let pixel = image::Rgb::<u8>([pixel.r as u8, pixel.g as u8, pixel.b as u8]);
+                img.put_pixel(x as u32, y as u32, pixel);
+            }
+        }
+        img.save(path).map_err(io::Error::other)
+    }
+}
+
+/// The side length of the square tiles into which the image is split.
+const TILE_SIZE: usize = 32;
+
+/// Renders images of a world as seen through a camera.
+pub struct Renderer {
+    pub settings: RenderSettings,
+    /// The number of threads rendering the image.
+    pub n_threads: usize,
+    pub background: Box<dyn Background>,
+}
+
+impl Renderer {
+    /// Creates a renderer that uses as many threads as there are cores and
+    /// the sky as the background.
+    pub fn new(settings: RenderSettings) -> Self {
+        Self {
+            settings,
+            n_threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            background: Box::new(GradientBackground::sky()),
+        }
+    }
+
+    /// Renders the image by splitting it into tiles that are handed out to
+    /// `n_threads` worker threads as they become idle, then assembling the
+    /// rendered tiles into a single framebuffer.
+    ///
+    /// Each pixel is sampled with its own `Sampler` derived from the seed, so
+    /// the result does not depend on the number of threads.
+    pub fn render<T: Hit>(&self, world: &T, cam: &Camera) -> Framebuffer {
+        let settings = &self.settings;
+        let lights = LightList::new(world);
+        let width = settings.width;
+        let height = settings.height;
+        let n_tiles_x = width.div_ceil(TILE_SIZE);
+        let n_tiles_y = height.div_ceil(TILE_SIZE);
+        let n_tiles = n_tiles_x * n_tiles_y;
+        let next_tile = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+
+        let mut pixels = vec![Rgb { r: 0.0, g: 0.0, b: 0.0 }; width * height];
+        thread::scope(|scope| {
+            for _ in 0..self.n_threads.max(1) {
+                let sender = sender.clone();
+                let next_tile = &next_tile;
+                let lights = &lights;
+                scope.spawn(move || loop {
+                    let tile = next_tile.fetch_add(1, Ordering::Relaxed);
+                    if tile >= n_tiles {
+                        break;
+                    }
+                    let x0 = (tile % n_tiles_x) * TILE_SIZE;
+                    let y0 = (tile / n_tiles_x) * TILE_SIZE;
+                    let x1 = (x0 + TILE_SIZE).min(width);
+                    let y1 = (y0 + TILE_SIZE).min(height);
+
+                    let mut tile_pixels = Vec::with_capacity((x1 - x0) * (y1 - y0));
+                    for y in y0..y1 {
+                        for x in x0..x1 {
+                            tile_pixels.push(self.render_pixel(world, lights, cam, x, y));
+                        }
+                    }
+                    if sender.send((x0, y0, x1, y1, tile_pixels)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+
+            for (x0, y0, x1, y1, tile_pixels) in receiver.iter() {
+                let tile_width = x1 - x0;
+                for y in y0..y1 {
+                    // Image rows are written top to bottom, while `y` grows
+                    // upwards.
+                    let row = (height - 1 - y) * width;
+                    let src = (y - y0) * tile_width;
+                    pixels[row + x0..row + x1]
+                        .copy_from_slice(&tile_pixels[src..src + tile_width]);
+                }
+            }
+        });
+
+        Framebuffer {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn render_pixel<T: Hit>(
+        &self,
+        world: &T,
+        lights: &LightList,
+        cam: &Camera,
+        x: usize,
+        y: usize,
+    ) -> Rgb {
+        let settings = &self.settings;
+        let mut sampler = Sampler::for_pixel(settings.seed, x, y);
+        // Anti-aliasing
+        let mut col = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
+        for _ in 0..settings.n_samples {
+            let u = (x as f32 + sampler.next_f32()) / settings.width as f32;
+            let v = (y as f32 + sampler.next_f32()) / settings.height as f32;
+            let ray = cam.ray(u, v, &mut sampler);
+            col += self.compute_color(&ray, world, lights, &mut sampler);
+        }
+        col /= settings.n_samples as f32;
+        let col = Rgb::from(col).gamma_correct().clamp();
+        Rgb::from(Vec3::from(col) * 255.99)
+    }
+
+    /// Returns the color of the light arriving along `ray`, following it as it
+    /// scatters off the objects of `world` at most `settings.max_depth` times.
+    ///
+    /// The light arriving directly from emissive objects at each non-specular
+    /// surface is estimated twice: by sampling a direction towards one of
+    /// `lights` and by following the direction sampled by the surface's
+    /// material. The two estimates are combined with multiple importance
+    /// sampling, weighting each by the power heuristic, so that small lights
+    /// are found by light sampling and glossy reflections of large ones by
+    /// material sampling.
+    pub fn compute_color<T: Hit>(
+        &self,
+        ray: &Ray,
+        world: &T,
+        lights: &LightList,
+        sampler: &mut Sampler,
+    ) -> Vec3 {
+        self.trace(ray, world, lights, sampler, 0, None)
+    }
+
+    /// `bsdf_pdf` is the density with which `ray` was sampled by the material
+    /// it scattered off, or `None` if it is a camera ray or was scattered
+    /// specularly, in which case the lights it hits were not sampled.
+    fn trace<T: Hit>(
+        &self,
+        ray: &Ray,
+        world: &T,
+        lights: &LightList,
+        sampler: &mut Sampler,
+        depth: usize,
+        bsdf_pdf: Option<f32>,
+    ) -> Vec3 {
+        let neutral = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
+        // See if the ray hits the world, otherwise paint the background.
+        let hit = match world.hit(ray, 0.001, f32::MAX) {
+            Some(hit) => hit,
+            None => return self.background.color(ray),
+        };
+
+        let mut emitted = hit.material.emitted(&hit);
+        if let Some(bsdf_pdf) = bsdf_pdf {
+            let light_pdf = lights.pdf(ray.origin, ray.direction);
+            emitted *= power_heuristic(bsdf_pdf, light_pdf);
+        }
+        if depth >= self.settings.max_depth {
+            return emitted;
+        }
+        let scatter = match hit.material.scatter(ray, &hit, sampler) {
+            Some(scatter) => scatter,
+            None => return emitted,
+        };
+        if scatter.is_specular {
+            let indirect = self.trace(&scatter.ray, world, lights, sampler, depth + 1, None);
+            return emitted + scatter.attenuation * indirect;
+        }
+
+        let mut direct = neutral;
+        if let Some(direction) = lights.sample_direction(hit.point, sampler) {
+            let light_pdf = lights.pdf(hit.point, direction);
+            let f = hit.material.eval(ray, &hit, direction);
+            if light_pdf > 0.0 && f != neutral {
+                let shadow_ray = Ray {
+                    origin: hit.point,
+                    direction,
+                    time: ray.time,
+                };
+                if let Some(light_hit) = world.hit(&shadow_ray, 0.001, f32::MAX) {
+                    let bsdf_pdf = hit.material.pdf(ray, &hit, direction);
+                    let weight = power_heuristic(light_pdf, bsdf_pdf);
+                    direct = f * light_hit.material.emitted(&light_hit) * weight / light_pdf;
+                }
+            }
+        }
+
+        let pdf = Some(scatter.pdf);
+        let indirect = self.trace(&scatter.ray, world, lights, sampler, depth + 1, pdf);
+        emitted + direct + scatter.attenuation * indirect
+    }
+}
+
+/// The weight of a sample taken with density `pdf` from one strategy, when
+/// the same direction could also have been sampled with density `other_pdf`
+/// from another, as per Veach's power heuristic with an exponent of 2.
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let a = pdf * pdf;
+    let b = other_pdf * other_pdf;
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use super::*;
+    use raytracer::scenes::{cornell_scene, cornell_smoke_scene};
+
+    #[test]
+    fn output_does_not_depend_on_thread_count() {
+        // Several tiles of a scene whose media and lights draw from the
+        // samplers in different ways.
+        let settings = RenderSettings {
+            width: 70,
+            height: 40,
+            n_samples: 2,
+            ..RenderSettings::default()
+        };
+        let render = |n_threads| {
+            let scene = cornell_smoke_scene(settings);
+            let renderer = Renderer {
+                settings,
+                n_threads,
+                background: scene.background,
+            };
+            renderer.render(&scene.objects, &Camera::new(scene.camera))
+        };
+        let single = render(1);
+        assert!(single == render(3));
+        assert!(single == render(8));
+    }
+
+    #[test]
+    fn saved_components_do_not_exceed_255() {
+        // The light of the Cornell box is far brighter than can be displayed.
+        let settings = RenderSettings {
+            width: 40,
+            height: 30,
+            n_samples: 2,
+            ..RenderSettings::default()
+        };
+        let scene = cornell_scene(settings);
+        let renderer = Renderer {
+            settings,
+            n_threads: 1,
+            background: scene.background,
+        };
+        let image = renderer.render(&scene.objects, &Camera::new(scene.camera));
+        let path = env::temp_dir().join(format!("raytracer-render-{}.ppm", process::id()));
+        image.save_ppm(&path).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let values: Vec<i32> = contents
+            .lines()
+            .skip(3)
+            .flat_map(|line| line.split_whitespace())
+            .map(|value| value.parse().unwrap())
+            .collect();
+        assert_eq!(values.len(), 3 * 40 * 30);
+        assert!(values.contains(&255));
+        assert!(values.iter().all(|&value| (0..=255).contains(&value)));
+    }
+}
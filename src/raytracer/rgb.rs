@@ -1,6 +1,6 @@
 use raytracer::vec3::Vec3;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Rgb {
     pub r: f32,
     pub g: f32,
@@ -16,6 +16,16 @@ impl Rgb {
         }
     }
 
+    /// Clamps each component to the range 0 to 1, the brightest that can be
+    /// displayed, which light sources and the surfaces they light exceed.
+    pub fn clamp(&self) -> Rgb {
+        Rgb {
+            r: self.r.clamp(0.0, 1.0),
+            g: self.g.clamp(0.0, 1.0),
+            b: self.b.clamp(0.0, 1.0),
+        }
+    }
+
     pub fn gamma_correct(&self) -> Rgb {
         Rgb {
             r: self.r.sqrt(),
@@ -0,0 +1,309 @@
+//! Real roots of the low degree polynomials that intersecting rays with
+//! surfaces comes down to.
+
+use std::iter;
+
+/// Values within this fraction of the magnitude of a polynomial's terms are
+/// considered zero. This decides whether a polynomial merely approaching zero
+/// at a turning point touches it, i.e. whether a grazing ray hits a surface.
+const TOUCH_EPSILON: f64 = 1e-12;
+/// Newton's method is stopped once the bracket around a root is this small,
+/// relative to the root.
+const CONVERGENCE_EPSILON: f64 = 1e-14;
+const MAX_ITERATIONS: usize = 100;
+
+/// The real roots of a polynomial, in increasing order. Repeated roots are
+/// only listed once.
+#[derive(Debug, Copy, Clone)]
+pub struct Roots {
+    values: [f64; 4],
+    len: usize,
+}
+
+impl Roots {
+    fn new() -> Self {
+        Self { values: [0.0; 4], len: 0 }
+    }
+
+    fn push(&mut self, x: f64) {
+        self.values[self.len] = x;
+        self.len += 1;
+    }
+
+    pub fn as_slice(&self) -> &[f64] {
+        &self.values[..self.len]
+    }
+}
+
+/// Solves `a*x^2 + b*x + c = 0`.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Roots {
+    let mut roots = Roots::new();
+    if a == 0.0 {
+        if b != 0.0 {
+            roots.push(-c / b);
+        }
+        return roots;
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return roots;
+    }
+    if discriminant == 0.0 {
+        roots.push(-0.5 * b / a);
+        return roots;
+    }
+    // Computing the root of larger magnitude first and the other from their
+    // product avoids the cancellation in `-b + sqrt(discriminant)` when the
+    // two are close.
+    let q = -0.5 * (b + discriminant.sqrt().copysign(b));
+    let (x0, x1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+    roots.push(x0.min(x1));
+    roots.push(x0.max(x1));
+    roots
+}
+
+/// Solves `a*x^4 + b*x^3 + c*x^2 + d*x + e = 0`, with `a` non-zero.
+///
+/// Rather than the closed-form solution, whose cancellations lose too much
+/// precision for rays grazing a surface, the roots are bracketed between the
+/// turning points of the polynomial, which are the roots of its derivative
+/// and found the same way, and then refined by Newton's method.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Roots {
+    real_roots(&[a, b, c, d, e])
+}
+
+/// Finds the real roots of the polynomial of degree 4 or less whose
+/// coefficients are given from the highest power down.
+fn real_roots(coeffs: &[f64]) -> Roots {
+    let degree = coeffs.len() - 1;
+    if degree <= 2 || coeffs[0] == 0.0 {
+        return match degree {
+            0 => Roots::new(),
+            1 => solve_quadratic(0.0, coeffs[0], coeffs[1]),
+            2 => solve_quadratic(coeffs[0], coeffs[1], coeffs[2]),
+            _ => real_roots(&coeffs[1..]),
+        };
+    }
+
+    let mut derivative = [0.0; 4];
+    for (i, c) in coeffs[..degree].iter().enumerate() {
+        derivative[i] = c * (degree - i) as f64;
+    }
+    let turning_points = real_roots(&derivative[..degree]);
+    // Cauchy's bound, beyond which there are no roots.
+    let bound = 1.0 + coeffs[1..].iter().fold(0.0f64, |m, c| m.max((c / coeffs[0]).abs()));
+
+    // Between consecutive turning points the polynomial is monotonic, so each
+    // interval holds a root if and only if the polynomial changes sign in it.
+    let mut roots = Roots::new();
+    let mut lo = -bound;
+    let mut p_lo = eval(coeffs, lo);
+    let ends = turning_points.as_slice().iter().map(|&x| (x, true));
+    for (x, is_turning_point) in ends.chain(iter::once((bound, false))) {
+        let x = x.clamp(-bound, bound);
+        let mut p = eval(coeffs, x);
+        if is_turning_point && p.abs() <= TOUCH_EPSILON * magnitude(coeffs, x) {
+            p = 0.0;
+        }
+        if p_lo != 0.0 && p != 0.0 && (p_lo < 0.0) != (p < 0.0) {
+            roots.push(refine(coeffs, lo, x, p_lo));
+        }
+        if p == 0.0 && roots.as_slice().last() != Some(&x) {
+            roots.push(x);
+        }
+        lo = x;
+        p_lo = p;
+    }
+    roots
+}
+
+/// Evaluates the polynomial at `x` by Horner's method.
+fn eval(coeffs: &[f64], x: f64) -> f64 {
+    coeffs.iter().fold(0.0, |acc, c| acc * x + c)
+}
+
+/// Evaluates the polynomial's derivative at `x`.
+fn eval_derivative(coeffs: &[f64], x: f64) -> f64 {
+    let degree = coeffs.len() - 1;
+    coeffs[..degree]
+        .iter()
+        .enumerate()
+        .fold(0.0, |acc, (i, c)| acc * x + c * (degree - i) as f64)
+}
+
+/// The sum of the absolute values of the polynomial's terms at `x`, against
+/// which round-off in its value is measured.
+fn magnitude(coeffs: &[f64], x: f64) -> f64 {
+    coeffs.iter().fold(0.0, |acc, c| acc * x.abs() + c.abs())
+}
+
+/// Finds the root in the interval from `lo` to `hi`, at whose ends the
+/// polynomial has opposite signs, using Newton's method and falling back to
+/// bisection whenever a step leaves the interval.
+fn refine(coeffs: &[f64], lo: f64, hi: f64, p_lo: f64) -> f64 {
+    let (mut lo, mut hi) = (lo, hi);
+    let lo_is_negative = p_lo < 0.0;
+    let mut x = 0.5 * (lo + hi);
+    for _ in 0..MAX_ITERATIONS {
+        let p = eval(coeffs, x);
+        if p == 0.0 {
+            return x;
+        }
+        if (p < 0.0) == lo_is_negative {
+            lo = x;
+        } else {
+            hi = x;
+        }
+        if hi - lo <= CONVERGENCE_EPSILON * x.abs().max(1.0) {
+            break;
+        }
+        let step = x - p / eval_derivative(coeffs, x);
+        x = if step > lo && step < hi { step } else { 0.5 * (lo + hi) };
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(roots: Roots, expected: &[f64]) {
+        let roots = roots.as_slice();
+        assert_eq!(roots.len(), expected.len(), "roots {:?}, expected {:?}", roots, expected);
+        for (x, e) in roots.iter().zip(expected) {
+            assert!((x - e).abs() < 1e-9, "roots {:?}, expected {:?}", roots, expected);
+        }
+    }
+
+    /// The coefficients of the quartic `(x - r0)(x - r1)(x - r2)(x - r3)`.
+    fn from_roots(r: [f64; 4]) -> [f64; 5] {
+        let mut c = [1.0, 0.0, 0.0, 0.0, 0.0];
+        for (i, root) in r.iter().enumerate() {
+            for j in (1..i + 2).rev() {
+                c[j] -= root * c[j - 1];
+            }
+        }
+        c
+    }
+
+    fn solve(c: [f64; 5]) -> Roots {
+        solve_quartic(c[0], c[1], c[2], c[3], c[4])
+    }
+
+    /// The quartic giving the offsets at which the ray from `origin` along
+    /// `direction` hits the torus around the y axis with radii `major` and
+    /// `minor`.
+    fn torus(origin: [f64; 3], direction: [f64; 3], major: f64, minor: f64) -> [f64; 5] {
+        let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+        let (o, d) = (origin, direction);
+        let m = dot(d, d);
+        let n = dot(o, d);
+        let k = dot(o, o) + major * major - minor * minor;
+        let r2 = 4.0 * major * major;
+        [
+            m * m,
+            4.0 * m * n,
+            4.0 * n * n + 2.0 * m * k - r2 * (d[0] * d[0] + d[2] * d[2]),
+            4.0 * n * k - 2.0 * r2 * (o[0] * d[0] + o[2] * d[2]),
+            k * k - r2 * (o[0] * o[0] + o[2] * o[2]),
+        ]
+    }
+
+    #[test]
+    fn quadratic() {
+        assert_roots(solve_quadratic(1.0, -3.0, 2.0), &[1.0, 2.0]);
+        assert_roots(solve_quadratic(1.0, -2.0, 1.0), &[1.0]);
+        assert_roots(solve_quadratic(1.0, 0.0, 1.0), &[]);
+        assert_roots(solve_quadratic(0.0, 2.0, -1.0), &[0.5]);
+        // Roots of very different magnitudes, where the textbook formula
+        // loses the smaller one to cancellation.
+        assert_roots(solve_quadratic(1.0, -1e8, 1.0), &[1e-8, 1e8]);
+    }
+
+    #[test]
+    fn distinct_roots() {
+        assert_roots(solve(from_roots([1.0, 2.0, 3.0, 4.0])), &[1.0, 2.0, 3.0, 4.0]);
+        assert_roots(solve(from_roots([-5.0, -0.5, 0.25, 10.0])), &[-5.0, -0.5, 0.25, 10.0]);
+        // x^4 - 5x^2 + 4 scaled by 3.
+        assert_roots(solve_quartic(3.0, 0.0, -15.0, 0.0, 12.0), &[-2.0, -1.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn no_real_roots() {
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0), &[]);
+        assert_roots(solve_quartic(1.0, 0.0, 2.0, 0.0, 1.0), &[]);
+    }
+
+    #[test]
+    fn repeated_roots() {
+        assert_roots(solve(from_roots([1.0, 1.0, 2.0, 3.0])), &[1.0, 2.0, 3.0]);
+        assert_roots(solve(from_roots([-1.0, -1.0, 1.0, 1.0])), &[-1.0, 1.0]);
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, 0.0), &[0.0]);
+    }
+
+    #[test]
+    fn lower_degree() {
+        // A zero leading coefficient leaves a cubic.
+        assert_roots(solve_quartic(0.0, 1.0, -6.0, 11.0, -6.0), &[1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn torus_through_center() {
+        // Along the x axis through a torus of radii 2 and 0.5.
+        let c = torus([-10.0, 0.0, 0.0], [1.0, 0.0, 0.0], 2.0, 0.5);
+        assert_roots(solve(c), &[7.5, 8.5, 11.5, 12.5]);
+    }
+
+    #[test]
+    fn torus_grazing_top() {
+        // Skimming the top of the tube, which the ray touches where it passes
+        // over the tube's center circle.
+        let c = torus([-10.0, 0.5, 0.0], [1.0, 0.0, 0.0], 2.0, 0.5);
+        assert_roots(solve(c), &[8.0, 12.0]);
+    }
+
+    #[test]
+    fn torus_nearly_grazing() {
+        // Just below the top of the tube, the ray passes through it, entering
+        // and leaving close to where it would touch it.
+        let c = torus([-10.0, 0.5 - 1e-6, 0.0], [1.0, 0.0, 0.0], 2.0, 0.5);
+        let roots = solve(c);
+        let roots = roots.as_slice();
+        assert_eq!(roots.len(), 4, "roots {:?}", roots);
+        for (x, e) in roots.iter().zip(&[8.0, 8.0, 12.0, 12.0]) {
+            assert!((x - e).abs() < 2e-3, "roots {:?}", roots);
+        }
+        // Every root lies on the torus.
+        for &x in roots {
+            let p = eval(&c, x) / magnitude(&c, x);
+            assert!(p.abs() < 1e-12, "residual {} at {}", p, x);
+        }
+
+        // And just above it, the ray misses.
+        let c = torus([-10.0, 0.5 + 1e-6, 0.0], [1.0, 0.0, 0.0], 2.0, 0.5);
+        assert_roots(solve(c), &[]);
+    }
+
+    #[test]
+    fn torus_grazing_outside() {
+        // Tangent to the outside of the torus at (0, 0, 2.5).
+        let c = torus([-10.0, 0.0, 2.5], [1.0, 0.0, 0.0], 2.0, 0.5);
+        assert_roots(solve(c), &[10.0]);
+        // Tangent to the inside of the tube at (0, 0, 1.5), having entered
+        // the tube before touching it and leaving it after.
+        let c = torus([-10.0, 0.0, 1.5], [1.0, 0.0, 0.0], 2.0, 0.5);
+        assert_roots(solve(c), &[8.0, 10.0, 12.0]);
+    }
+
+    #[test]
+    fn torus_oblique() {
+        // Every root of an oblique ray lies on the torus.
+        let c = torus([3.0, 4.0, -5.0], [-0.3, -0.5, 0.6], 1.0, 0.4);
+        let roots = solve(c);
+        assert!(!roots.as_slice().is_empty());
+        for &x in roots.as_slice() {
+            let p = eval(&c, x) / magnitude(&c, x);
+            assert!(p.abs() < 1e-12, "residual {} at {}", p, x);
+        }
+    }
+}
@@ -0,0 +1,561 @@
+use raytracer::aabb::Aabb;
+use raytracer::hit::*;
+use raytracer::light::Light;
+use raytracer::ray::Ray;
+use raytracer::vec3::Vec3;
+
+/// The estimated cost of testing a ray against a node's bounding box, relative
+/// to the cost of intersecting a primitive.
+const TRAVERSAL_COST: f32 = 0.125;
+const INTERSECTION_COST: f32 = 1.0;
+/// The deepest a tree may get, which also bounds the traversal stack.
+const MAX_DEPTH: usize = 64;
+
+/// Parameters of the surface area heuristic builder.
+#[derive(Debug, Copy, Clone)]
+pub struct SahInfo {
+    /// The number of buckets into which primitive centroids are binned along
+    /// the split axis when evaluating candidate splits.
+    pub n_bins: usize,
+    /// Nodes with at most this many primitives become leaves if splitting them
+    /// is not expected to be cheaper.
+    pub max_leaf_size: usize,
+}
+
+impl Default for SahInfo {
+    fn default() -> Self {
+        Self {
+            n_bins: 12,
+            max_leaf_size: 4,
+        }
+    }
+}
+
+/// Statistics gathered while building a `SahBvh`.
+#[derive(Debug, Copy, Clone)]
+pub struct BvhStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    /// The number of nodes on the longest path from the root to a leaf.
+    pub depth: usize,
+    /// The expected cost of tracing a ray through the tree, which is the sum
+    /// of each node's traversal or intersection cost weighted by the
+    /// probability of a ray hitting it, i.e. by its surface area relative to
+    /// the root's.
+    pub sah_cost: f32,
+}
+
+impl BvhStats {
+    /// The statistics of a plain list of `n` objects, as if it were a tree
+    /// consisting of a single leaf, for comparison.
+    pub fn linear(n: usize) -> Self {
+        Self {
+            node_count: 1,
+            leaf_count: 1,
+            depth: 1,
+            sah_cost: n as f32 * INTERSECTION_COST,
+        }
+    }
+}
+
+/// A bounding volume hierarchy built with the binned surface area heuristic.
+///
+/// Rather than being linked with pointers, the nodes are laid out in a single
+/// array in depth first order, so that a node's first child immediately
+/// follows it and only the index of the second one needs to be stored. The
+/// tree is traversed with an explicit stack instead of recursion.
+pub struct SahBvh<T: Hit = Box<dyn Hit>> {
+    nodes: Vec<LinearNode>,
+    /// The primitives, ordered such that each leaf refers to a contiguous
+    /// range.
+    primitives: Vec<T>,
+    /// Objects that cannot be bounded are kept outside the tree and are always
+    /// tested.
+    unbounded: Vec<T>,
+    stats: BvhStats,
+}
+
+struct LinearNode {
+    bbox: Aabb,
+    /// For leaves the index of the first primitive, for interior nodes the
+    /// index of the second child.
+    offset: usize,
+    /// Zero for interior nodes.
+    n_primitives: usize,
+    /// The axis along which an interior node's children were split.
+    axis: usize,
+}
+
+#[derive(Copy, Clone)]
+struct Bin {
+    count: usize,
+    bbox: Option<Aabb>,
+}
+
+struct BuildPrimitive {
+    index: usize,
+    bbox: Aabb,
+    centroid: Vec3,
+}
+
+impl<T: Hit> SahBvh<T> {
+    pub fn new(objects: Vec<T>, info: SahInfo) -> Self {
+        let mut bounded = Vec::with_capacity(objects.len());
+        let mut build = Vec::with_capacity(objects.len());
+        let mut unbounded = Vec::new();
+        for object in objects {
+            match object.bounding_box() {
+                Some(bbox) => {
+                    build.push(BuildPrimitive {
+                        index: bounded.len(),
+                        bbox,
+                        centroid: bbox.centroid(),
+                    });
+                    bounded.push(Some(object));
+                }
+                None => unbounded.push(object),
+            }
+        }
+
+        let mut bvh = Self {
+            nodes: Vec::new(),
+            primitives: Vec::with_capacity(bounded.len()),
+            unbounded,
+            stats: BvhStats {
+                node_count: 0,
+                leaf_count: 0,
+                depth: 0,
+                sah_cost: 0.0,
+            },
+        };
+        if build.is_empty() {
+            return bvh;
+        }
+
+        let info = SahInfo {
+            n_bins: info.n_bins.max(2),
+            max_leaf_size: info.max_leaf_size.max(1),
+        };
+        let mut order = Vec::with_capacity(build.len());
+        bvh.build(&info, &mut build, &mut order, 1);
+        // Move the primitives into the order in which the leaves refer to them.
+        bvh.primitives = order
+            .into_iter()
+            .map(|i| bounded[i].take().unwrap())
+            .collect();
+
+        let root_area = bvh.nodes[0].bbox.surface_area();
+        bvh.stats.node_count = bvh.nodes.len();
+        bvh.stats.sah_cost = bvh
+            .nodes
+            .iter()
+            .map(|node| {
+                let p = if root_area > 0.0 {
+                    node.bbox.surface_area() / root_area
+                } else {
+                    1.0
+                };
+                if node.n_primitives > 0 {
+                    p * INTERSECTION_COST * node.n_primitives as f32
+                } else {
+                    p * TRAVERSAL_COST
+                }
+            })
+            .sum();
+        bvh
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.stats
+    }
+
+    /// Recursively builds the subtree over `prims`, appending its nodes in
+    /// depth first order and the indices of its primitives to `order`.
+    fn build(
+        &mut self,
+        info: &SahInfo,
+        prims: &mut [BuildPrimitive],
+        order: &mut Vec<usize>,
+        depth: usize,
+    ) {
+        self.stats.depth = self.stats.depth.max(depth);
+        let bbox = prims
+            .iter()
+            .skip(1)
+            .fold(prims[0].bbox, |acc, p| Aabb::surrounding(acc, p.bbox));
+        let node_index = self.nodes.len();
+        self.nodes.push(LinearNode {
+            bbox,
+            offset: 0,
+            n_primitives: 0,
+            axis: 0,
+        });
+
+        match find_split(info, prims, bbox, depth) {
+            Some((axis, mid)) => {
+                let (left, right) = prims.split_at_mut(mid);
+                self.build(info, left, order, depth + 1);
+                self.nodes[node_index].offset = self.nodes.len();
+                self.nodes[node_index].axis = axis;
+                self.build(info, right, order, depth + 1);
+            }
+            None => {
+                let node = &mut self.nodes[node_index];
+                node.offset = order.len();
+                node.n_primitives = prims.len();
+                order.extend(prims.iter().map(|p| p.index));
+                self.stats.leaf_count += 1;
+            }
+        }
+    }
+}
+
+/// Partitions `prims` along the split that minimizes the surface area
+/// heuristic, returning the split axis and the index of the first
+/// primitive on the far side, or `None` if the node should be a leaf.
+fn find_split(
+    info: &SahInfo,
+    prims: &mut [BuildPrimitive],
+    bbox: Aabb,
+    depth: usize,
+) -> Option<(usize, usize)> {
+    let n = prims.len();
+    if n == 1 || depth >= MAX_DEPTH {
+        return None;
+    }
+
+    let mut centroid_bounds = Aabb {
+        min: prims[0].centroid,
+        max: prims[0].centroid,
+    };
+    for p in prims.iter() {
+        let c = p.centroid;
+        centroid_bounds = Aabb::surrounding(centroid_bounds, Aabb { min: c, max: c });
+    }
+    let axis = centroid_bounds.longest_axis();
+    let lo = centroid_bounds.min[axis];
+    let extent = centroid_bounds.max[axis] - lo;
+    if extent <= 0.0 {
+        // All centroids coincide so no split can separate them.
+        return None;
+    }
+
+    let n_bins = info.n_bins;
+    let bin_of = |p: &BuildPrimitive| {
+        let b = (n_bins as f32 * (p.centroid[axis] - lo) / extent) as usize;
+        b.min(n_bins - 1)
+    };
+    let mut bins = vec![Bin { count: 0, bbox: None }; n_bins];
+    for p in prims.iter() {
+        let bin = &mut bins[bin_of(p)];
+        bin.count += 1;
+        bin.bbox = Some(bin.bbox.map_or(p.bbox, |b| Aabb::surrounding(b, p.bbox)));
+    }
+
+    // Sweep from the right to get the area and count of every suffix of
+    // bins, then from the left to evaluate each of the `n_bins - 1` splits.
+    let mut right_area = vec![0.0; n_bins];
+    let mut right_count = vec![0; n_bins];
+    let mut acc: Option<Aabb> = None;
+    let mut count = 0;
+    for i in (1..n_bins).rev() {
+        if let Some(b) = bins[i].bbox {
+            acc = Some(acc.map_or(b, |a| Aabb::surrounding(a, b)));
+        }
+        count += bins[i].count;
+        right_area[i] = acc.map_or(0.0, |a| a.surface_area());
+        right_count[i] = count;
+    }
+
+    let area = bbox.surface_area();
+    let mut best: Option<(f32, usize)> = None;
+    let mut acc: Option<Aabb> = None;
+    let mut count = 0;
+    for i in 0..n_bins - 1 {
+        if let Some(b) = bins[i].bbox {
+            acc = Some(acc.map_or(b, |a| Aabb::surrounding(a, b)));
+        }
+        count += bins[i].count;
+        if count == 0 || right_count[i + 1] == 0 {
+            continue;
+        }
+        let left_area = acc.map_or(0.0, |a| a.surface_area());
+        let cost = TRAVERSAL_COST
+            + INTERSECTION_COST
+                * (count as f32 * left_area + right_count[i + 1] as f32 * right_area[i + 1])
+                / area;
+        let better = match best {
+            Some((best_cost, _)) => cost < best_cost,
+            None => true,
+        };
+        if better {
+            best = Some((cost, i));
+        }
+    }
+
+    let (cost, split_bin) = best?;
+    let leaf_cost = INTERSECTION_COST * n as f32;
+    if n <= info.max_leaf_size && cost >= leaf_cost {
+        return None;
+    }
+
+    // Partition the primitives in place so that those in bins up to and
+    // including `split_bin` come first.
+    let mut mid = 0;
+    for i in 0..n {
+        if bin_of(&prims[i]) <= split_bin {
+            prims.swap(i, mid);
+            mid += 1;
+        }
+    }
+    Some((axis, mid))
+}
+
+impl<T: Hit> Hit for SahBvh<T> {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        let mut record = None;
+        let mut closest = max;
+
+        if !self.nodes.is_empty() {
+            let dir_is_neg = [
+                ray.direction.x < 0.0,
+                ray.direction.y < 0.0,
+                ray.direction.z < 0.0,
+            ];
+            let mut stack = [0; MAX_DEPTH];
+            let mut stack_len = 0;
+            let mut current = 0;
+            loop {
+                let node = &self.nodes[current];
+                if node.bbox.hit(ray, min, closest) {
+                    if node.n_primitives > 0 {
+                        let prims = &self.primitives[node.offset..node.offset + node.n_primitives];
+                        for prim in prims.iter() {
+                            if let Some(rec) = prim.hit(ray, min, closest) {
+                                closest = rec.t;
+                                record = Some(rec);
+                            }
+                        }
+                    } else {
+                        // Visit the child nearer to the ray's origin first, so
+                        // that the hits found there can cull the other child.
+                        if dir_is_neg[node.axis] {
+                            stack[stack_len] = current + 1;
+                            current = node.offset;
+                        } else {
+                            stack[stack_len] = node.offset;
+                            current += 1;
+                        }
+                        stack_len += 1;
+                        continue;
+                    }
+                }
+                if stack_len == 0 {
+                    break;
+                }
+                stack_len -= 1;
+                current = stack[stack_len];
+            }
+        }
+
+        for object in self.unbounded.iter() {
+            if let Some(rec) = object.hit(ray, min, closest) {
+                closest = rec.t;
+                record = Some(rec);
+            }
+        }
+        record
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if self.unbounded.is_empty() {
+            self.nodes.first().map(|node| node.bbox)
+        } else {
+            None
+        }
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        for object in self.primitives.iter().chain(self.unbounded.iter()) {
+            object.lights(lights);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use raytracer::rect::{XyRect, XzRect};
+    use raytracer::sampler::Sampler;
+    use raytracer::scatter::{Lambertian, Scatter};
+    use raytracer::sphere::Sphere;
+    use raytracer::texture::SolidColor;
+
+    /// A material of its own, by which the object hit can be told apart.
+    fn material() -> Arc<dyn Scatter> {
+        let color = Vec3 { x: 0.5, y: 0.5, z: 0.5 };
+        Arc::new(Lambertian { albedo: Box::new(SolidColor { color }) })
+    }
+
+    fn sphere(x: f32, y: f32, z: f32, radius: f32) -> Arc<dyn Hit> {
+        Arc::new(Sphere {
+            center: Vec3 { x, y, z },
+            radius,
+            material: material(),
+        })
+    }
+
+    /// Checks that rays starting anywhere within 12 units of the origin hit
+    /// the same objects at the same distances through a tree of `objects` as
+    /// through a plain list of them.
+    fn assert_hits_match(objects: &[Arc<dyn Hit>], info: SahInfo) {
+        let boxed = || {
+            objects
+                .iter()
+                .map(|object| Box::new(Arc::clone(object)) as Box<dyn Hit>)
+                .collect::<Vec<_>>()
+        };
+        let list = boxed();
+        let bvh = SahBvh::new(boxed(), info);
+
+        let material = |material: &dyn Scatter| material as *const dyn Scatter as *const u8;
+        let mut sampler = Sampler::new(9);
+        let mut n_hits = 0;
+        for _ in 0..5_000 {
+            let origin = 12.0 * sampler.in_unit_sphere();
+            let ray = Ray {
+                origin,
+                direction: sampler.unit_vector(),
+                time: 0.0,
+            };
+            match (list.hit(&ray, 0.001, f32::MAX), bvh.hit(&ray, 0.001, f32::MAX)) {
+                (None, None) => {}
+                (Some(expected), Some(actual)) => {
+                    assert_eq!(expected.t, actual.t);
+                    assert!(material(expected.material) == material(actual.material));
+                    n_hits += 1;
+                }
+                (expected, actual) => panic!(
+                    "list hit at {:?}, BVH at {:?}",
+                    expected.map(|rec| rec.t),
+                    actual.map(|rec| rec.t)
+                ),
+            }
+        }
+        assert!(n_hits > 50, "only {} rays hit", n_hits);
+    }
+
+    #[test]
+    fn hits_match_the_list() {
+        let mut sampler = Sampler::new(1);
+        let objects: Vec<_> = (0..300)
+            .map(|_| {
+                let c = 8.0 * sampler.in_unit_sphere();
+                sphere(c.x, c.y, c.z, 0.1 + 0.5 * sampler.next_f32())
+            })
+            .collect();
+        assert_hits_match(&objects, SahInfo::default());
+        // Trees of every shape, down to leaves of single objects split into
+        // as few bins as possible.
+        let info = SahInfo {
+            n_bins: 2,
+            max_leaf_size: 1,
+        };
+        assert_hits_match(&objects, info);
+        let info = SahInfo {
+            n_bins: 32,
+            max_leaf_size: 16,
+        };
+        assert_hits_match(&objects, info);
+    }
+
+    #[test]
+    fn hits_match_the_list_with_duplicate_centroids() {
+        // Concentric spheres, among clusters of them sharing centers, cannot be
+        // separated by any split.
+        let mut objects = Vec::new();
+        for i in 0..4 {
+            let x = 4.0 * i as f32 - 6.0;
+            for j in 0..6 {
+                objects.push(sphere(x, 0.0, 0.0, 0.3 + 0.25 * j as f32));
+            }
+        }
+        let info = SahInfo {
+            n_bins: 12,
+            max_leaf_size: 1,
+        };
+        assert_hits_match(&objects, info);
+        assert_hits_match(&objects[..6], info);
+    }
+
+    #[test]
+    fn hits_match_the_list_with_a_single_object() {
+        assert_hits_match(&[sphere(1.0, 2.0, -1.0, 3.0)], SahInfo::default());
+    }
+
+    #[test]
+    fn hits_match_the_list_with_flat_bounds() {
+        // A floor of coplanar tiles, whose boxes are flat along y, and a wall
+        // of them flat along z.
+        let mut objects: Vec<Arc<dyn Hit>> = Vec::new();
+        for i in 0..8 {
+            for j in 0..8 {
+                let (x0, y0) = (2.0 * i as f32 - 8.0, 2.0 * j as f32 - 8.0);
+                objects.push(Arc::new(XzRect {
+                    x0,
+                    x1: x0 + 1.5,
+                    z0: y0,
+                    z1: y0 + 1.5,
+                    k: -2.0,
+                    material: material(),
+                }));
+                objects.push(Arc::new(XyRect {
+                    x0,
+                    x1: x0 + 1.5,
+                    y0,
+                    y1: y0 + 1.5,
+                    k: 3.0,
+                    material: material(),
+                }));
+            }
+        }
+        assert_hits_match(&objects, SahInfo::default());
+        let info = SahInfo {
+            n_bins: 4,
+            max_leaf_size: 1,
+        };
+        assert_hits_match(&objects, info);
+    }
+
+    fn stats(objects: Vec<Arc<dyn Hit>>) -> BvhStats {
+        SahBvh::new(objects, SahInfo::default()).stats()
+    }
+
+    #[test]
+    fn stats_describe_the_tree() {
+        // Two unit spheres ten apart are split into a leaf each, their boxes'
+        // areas of 24 each being a fraction of the root's area of 104.
+        let two = stats(vec![sphere(0.0, 0.0, 0.0, 1.0), sphere(10.0, 0.0, 0.0, 1.0)]);
+        assert_eq!((two.node_count, two.leaf_count, two.depth), (3, 2, 2));
+        assert!((two.sah_cost - (TRAVERSAL_COST + 48.0 / 104.0)).abs() < 1e-6);
+
+        // Objects sharing a centroid are left in a single leaf.
+        let same = stats((1..6).map(|i| sphere(1.0, 1.0, 1.0, i as f32)).collect());
+        assert_eq!((same.node_count, same.leaf_count, same.depth), (1, 1, 1));
+        assert_eq!(same.sah_cost, 5.0);
+
+        let linear = BvhStats::linear(5);
+        assert_eq!((linear.node_count, linear.leaf_count, linear.depth), (1, 1, 1));
+        assert_eq!(linear.sah_cost, 5.0);
+
+        let empty = stats(Vec::new());
+        assert_eq!((empty.node_count, empty.leaf_count, empty.depth), (0, 0, 0));
+    }
+}
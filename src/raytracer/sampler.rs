@@ -0,0 +1,100 @@
+use std::f32::consts::PI;
+
+use rand::prng::XorShiftRng;
+use rand::{Rng, SeedableRng};
+
+use raytracer::ray::Ray;
+use raytracer::vec3::*;
+
+/// The source of all random numbers used while rendering.
+///
+/// Rather than drawing from a thread local generator, every pixel gets its own
+/// sampler seeded from the global seed and the pixel's coordinates, so the
+/// same seed always produces the same image, no matter how many threads render
+/// it or in what order.
+pub struct Sampler {
+    rng: XorShiftRng,
+}
+
+impl Sampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: XorShiftRng::seed_from_u64(mix(seed)),
+        }
+    }
+
+    /// Returns the sampler for the pixel at `x` and `y`.
+    pub fn for_pixel(seed: u64, x: usize, y: usize) -> Self {
+        Self::new(mix(mix(seed) ^ x as u64) ^ y as u64)
+    }
+
+    /// Returns a sampler derived from the ray itself, for where random numbers
+    /// are needed but no sampler is at hand, such as when finding where a ray
+    /// scatters within a `ConstantMedium`. The same ray always gets the same
+    /// numbers, so it is hit the same way however many times it is traced.
+    pub fn for_ray(ray: &Ray) -> Self {
+        let (o, d) = (ray.origin, ray.direction);
+        let seed = [o.x, o.y, o.z, d.x, d.y, d.z, ray.time]
+            .iter()
+            .fold(0, |seed, x| mix(seed ^ x.to_bits() as u64));
+        Self::new(seed)
+    }
+
+    /// Returns a uniformly distributed number in the range `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        self.rng.gen()
+    }
+
+    pub fn in_unit_disk(&mut self) -> Vec3 {
+        loop {
+            let p = 2.0 * Vec3 { x: self.next_f32(), y: self.next_f32(), z: 0.0 }
+                - Vec3 { x: 1.0, y: 1.0, z: 0.0 };
+            if dot(p, p) < 1.0 {
+                return p;
+            }
+        }
+    }
+
+    /// Returns a point uniformly distributed within the unit sphere, by
+    /// picking a uniformly distributed direction and scaling it by the cube
+    /// root of a uniform number, as the volume within radius `r` grows with
+    /// `r^3`.
+    pub fn in_unit_sphere(&mut self) -> Vec3 {
+        self.unit_vector() * self.next_f32().cbrt()
+    }
+
+    /// Returns a direction uniformly distributed on the unit sphere.
+    pub fn unit_vector(&mut self) -> Vec3 {
+        let z = 1.0 - 2.0 * self.next_f32();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * self.next_f32();
+        Vec3 { x: r * phi.cos(), y: r * phi.sin(), z }
+    }
+
+    /// Returns a direction on the hemisphere around the z axis, distributed
+    /// in proportion to the cosine of its angle with the z axis, i.e. with a
+    /// density of `z / PI`.
+    ///
+    /// By Malley's method, this is a point uniformly distributed on the unit
+    /// disk projected up onto the hemisphere.
+    pub fn cosine_direction(&mut self) -> Vec3 {
+        let r1 = self.next_f32();
+        let r2 = self.next_f32();
+        let phi = 2.0 * PI * r1;
+        let r = r2.sqrt();
+        Vec3 {
+            x: phi.cos() * r,
+            y: phi.sin() * r,
+            z: (1.0 - r2).max(0.0).sqrt(),
+        }
+    }
+}
+
+/// The SplitMix64 finalizer, used to turn similar seeds (such as those of
+/// neighbouring pixels) into uncorrelated ones.
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -1,63 +1,114 @@
-extern crate rand;
-
+use raytracer::hit::HitRecord;
+use raytracer::onb::Onb;
+use raytracer::phase::PhaseFunction;
 use raytracer::ray::*;
+use raytracer::sampler::Sampler;
+use raytracer::texture::Texture;
 use raytracer::vec3::*;
-use rand::Rng;
 
 pub struct ScatterRecord {
+    /// The BSDF multiplied by the cosine term and divided by `pdf`, i.e. the
+    /// factor by which light arriving along `ray` is weighted.
     pub attenuation: Vec3,
     pub ray: Ray,
+    /// The probability density, with respect to solid angle, with which
+    /// `ray`'s direction was sampled. Unused if `is_specular` is set.
+    pub pdf: f32,
+    /// Set if the direction was sampled from a delta distribution (or one so
+    /// narrow it is treated as such), which cannot be evaluated for arbitrary
+    /// directions and so cannot be combined with light sampling.
+    pub is_specular: bool,
 }
 
-pub trait Scatter {
-    fn scatter(&self, ray: &Ray, point: Vec3, normal: Vec3) -> Option<ScatterRecord>;
+/// Describes how a material scatters an incoming ray. Like `Hit`, materials are
+/// shared between render threads.
+///
+/// The methods are given the `HitRecord` of the ray hitting the material, from
+/// which they take the point, normal and surface coordinates at which to
+/// evaluate it.
+pub trait Scatter: Send + Sync {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord>;
+
+    /// The light emitted by the material at the hit, which is none for all but
+    /// light sources.
+    fn emitted(&self, _hit: &HitRecord) -> Vec3 {
+        Vec3 { x: 0.0, y: 0.0, z: 0.0 }
+    }
+
+    /// Whether objects made of this material are light sources.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Evaluates how much of the light arriving from `direction` is scattered
+    /// along the reverse of `ray`, i.e. the BSDF multiplied by the cosine of
+    /// the angle between `direction` and the normal. This is zero for
+    /// specular materials, whose delta distributions are only ever sampled.
+    fn eval(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vec3) -> Vec3 {
+        Vec3 { x: 0.0, y: 0.0, z: 0.0 }
+    }
+
+    /// The probability density, with respect to solid angle, of `scatter`
+    /// sampling `direction`. This is zero for specular materials.
+    fn pdf(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vec3) -> f32 {
+        0.0
+    }
 }
 
 pub struct Lambertian {
-    pub albedo: Vec3,
+    pub albedo: Box<dyn Texture>,
 }
 
 impl Scatter for Lambertian {
-    fn scatter(&self, _: &Ray, point: Vec3, normal: Vec3) -> Option<ScatterRecord> {
-        let target = point + normal + rand_point_in_unit_sphere();
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        // Sampling directions in proportion to the cosine term makes it, and
+        // the BRDF's normalization by PI, cancel out of the attenuation.
+        let direction = Onb::from_w(hit.normal).local(sampler.cosine_direction());
         Some(ScatterRecord {
-            attenuation: self.albedo,
+            attenuation: self.albedo.value(hit.u, hit.v, hit.point),
             ray: Ray {
-                origin: point,
-                direction: target - point,
+                origin: hit.point,
+                direction,
+                time: ray.time,
             },
+            pdf: self.pdf(ray, hit, direction),
+            is_specular: false,
         })
     }
-}
 
-fn rand_point_in_unit_sphere() -> Vec3 {
-    // TODO optimize this
-    let mut rng = rand::thread_rng();
-    loop {
-        let v = 2.0 * Vec3 { x: rng.gen(), y: rng.gen(), z: rng.gen(), }
-            - Vec3 { x: 1.0, y: 1.0, z: 1.0 };
+    fn eval(&self, _: &Ray, hit: &HitRecord, direction: Vec3) -> Vec3 {
+        let cos = dot(hit.normal, direction.to_unit()).max(0.0);
+        self.albedo.value(hit.u, hit.v, hit.point) * cos / std::f32::consts::PI
+    }
 
-        if v.squared_len() < 1.0 {
-            return v;
-        }
+    fn pdf(&self, _: &Ray, hit: &HitRecord, direction: Vec3) -> f32 {
+        dot(hit.normal, direction.to_unit()).max(0.0) / std::f32::consts::PI
     }
 }
 
 pub struct Reflective {
-    pub albedo: Vec3,
+    pub albedo: Box<dyn Texture>,
     pub fuzz: f32,
 }
 
 impl Scatter for Reflective {
-    fn scatter(&self, ray: &Ray, point: Vec3, normal: Vec3) -> Option<ScatterRecord> {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let normal = hit.normal;
         let reflected = reflect(ray.direction.to_unit(), normal);
-        if dot(reflected, normal) > 0.0 {
+        let direction = reflected + self.fuzz * sampler.in_unit_sphere();
+        // Fuzz may scatter the reflection into the surface, which absorbs it.
+        if dot(direction, normal) > 0.0 {
             Some(ScatterRecord {
-                attenuation: self.albedo,
+                attenuation: self.albedo.value(hit.u, hit.v, hit.point),
                 ray: Ray {
-                    origin: point,
-                    direction: reflected + self.fuzz * rand_point_in_unit_sphere(),
-                }
+                    origin: hit.point,
+                    direction,
+                    time: ray.time,
+                },
+                // Even with fuzz the reflections are concentrated around the
+                // mirror direction, so they are treated as specular.
+                pdf: 0.0,
+                is_specular: true,
             })
         } else {
             None
@@ -86,50 +137,55 @@ pub struct Dielectric {
 }
 
 impl Scatter for Dielectric {
-    fn scatter(&self, ray: &Ray, point: Vec3, normal: Vec3) -> Option<ScatterRecord> {
-        let (outward_normal, ni_over_nt, cos) = {
-            // Determine whether the ray is in the refractive object and take
-            // the opposite of the surface normal if so.
-            if dot(ray.direction, normal) > 0.0 {
-                let outward_normal = -1.0 * normal;
-                let ni_over_nt = self.refraction_index;
-                let cos = self.refraction_index * dot(ray.direction, normal) / ray.direction.len();
-                (outward_normal, ni_over_nt, cos)
-            } else {
-                let outward_normal = normal;
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let (point, normal) = (hit.point, hit.normal);
+        let (ni_over_nt, cos) = {
+            // Determine whether the ray is leaving the refractive object,
+            // rather than entering it.
+            if hit.front_face {
                 let ni_over_nt = 1.0 / self.refraction_index;
                 let cos = -dot(ray.direction, normal) / ray.direction.len();
-                (outward_normal, ni_over_nt, cos)
+                (ni_over_nt, cos)
+            } else {
+                let ni_over_nt = self.refraction_index;
+                let cos = -ni_over_nt * dot(ray.direction, normal) / ray.direction.len();
+                (ni_over_nt, cos)
             }
         };
         let attenuation = Vec3 { x: 1.0, y: 1.0, z: 1.0 };
 
-        if let Some(refracted) = refract(ray.direction, outward_normal, ni_over_nt) {
+        if let Some(refracted) = refract(ray.direction, normal, ni_over_nt) {
             let scattered = {
                 // Dielectric materials have reflectivity that varies with
                 // angle, use Christophe Schlick's to get a polinomial
                 // approximation of the probability of reflectoin.
                 let reflection_prob = schlick(cos, self.refraction_index);
-                if rand::thread_rng().gen::<f32>() >= reflection_prob {
+                if sampler.next_f32() >= reflection_prob {
                     refracted
                 } else {
                     reflect(ray.direction, normal)
                 }
             };
             Some(ScatterRecord {
-                attenuation: attenuation,
+                attenuation,
                 ray: Ray {
                     origin: point,
                     direction: scattered,
+                    time: ray.time,
                 },
+                pdf: 0.0,
+                is_specular: true,
             })
         } else {
             Some(ScatterRecord {
-                attenuation: attenuation,
+                attenuation,
                 ray: Ray {
                     origin: point,
                     direction: reflect(ray.direction, normal),
+                    time: ray.time,
                 },
+                pdf: 0.0,
+                is_specular: true,
             })
         }
     }
@@ -151,3 +207,125 @@ fn schlick(cos: f32, refraction_index: f32) -> f32 {
     let r = r * r;
     r + (1.0 - r) * (1.0 - cos).powi(5)
 }
+
+/// The material of a medium, such as a `ConstantMedium`, which scatters the
+/// fraction `albedo` of the light reaching each point in the directions given
+/// by its `phase_function`, and absorbs the rest.
+pub struct Volume {
+    pub albedo: Box<dyn Texture>,
+    pub phase_function: Box<dyn PhaseFunction>,
+}
+
+impl Scatter for Volume {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let incoming = ray.direction.to_unit();
+        let direction = self.phase_function.sample(incoming, sampler);
+        let pdf = self.phase_function.pdf(incoming, direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let albedo = self.albedo.value(hit.u, hit.v, hit.point);
+        Some(ScatterRecord {
+            attenuation: albedo * self.phase_function.eval(incoming, direction) / pdf,
+            ray: Ray {
+                origin: hit.point,
+                direction,
+                time: ray.time,
+            },
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    /// Unlike a surface's, a medium's scattering has no cosine term.
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: Vec3) -> Vec3 {
+        let phase = self.phase_function.eval(ray.direction.to_unit(), direction.to_unit());
+        self.albedo.value(hit.u, hit.v, hit.point) * phase
+    }
+
+    fn pdf(&self, ray: &Ray, _: &HitRecord, direction: Vec3) -> f32 {
+        self.phase_function.pdf(ray.direction.to_unit(), direction.to_unit())
+    }
+}
+
+/// A material that emits light evenly in all directions and reflects none.
+/// Its color may exceed 1.0 to make it brighter than the surfaces it lights.
+pub struct DiffuseLight {
+    pub emit: Box<dyn Texture>,
+}
+
+impl Scatter for DiffuseLight {
+    fn scatter(&self, _: &Ray, _: &HitRecord, _: &mut Sampler) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn emitted(&self, hit: &HitRecord) -> Vec3 {
+        self.emit.value(hit.u, hit.v, hit.point)
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use raytracer::texture::SolidColor;
+
+    #[test]
+    fn lambertian_pdf_matches_its_sampling() {
+        // Multiple importance sampling weighs the directions `scatter` samples
+        // by `pdf`, so the fraction of samples within each band of angles from
+        // the normal must match the pdf's integral over that band, and the
+        // attenuation must be `eval` over `pdf`.
+        let albedo = Vec3 { x: 0.8, y: 0.5, z: 0.2 };
+        let material = Lambertian { albedo: Box::new(SolidColor { color: albedo }) };
+        let normal = Vec3 { x: -0.2, y: 0.9, z: 0.4 }.to_unit();
+        let hit = HitRecord {
+            t: 1.0,
+            point: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+            normal,
+            front_face: true,
+            u: 0.0,
+            v: 0.0,
+            material: &material,
+        };
+        let ray = Ray {
+            origin: normal,
+            direction: -1.0 * normal,
+            time: 0.0,
+        };
+        let basis = Onb::from_w(normal);
+
+        const BANDS: usize = 5;
+        let n = 200_000;
+        let mut counts = [0; BANDS];
+        let mut sampler = Sampler::new(3);
+        for _ in 0..n {
+            let record = material.scatter(&ray, &hit, &mut sampler).unwrap();
+            let direction = record.ray.direction;
+            let cos = dot(normal, direction.to_unit());
+            assert!(cos >= 0.0);
+            let expected = material.eval(&ray, &hit, direction) / record.pdf;
+            assert!((record.attenuation - expected).len() < 1e-3);
+            counts[((cos * BANDS as f32) as usize).min(BANDS - 1)] += 1;
+        }
+
+        let steps = 1000;
+        for (band, &count) in counts.iter().enumerate() {
+            let expected: f32 = (0..steps)
+                .map(|i| {
+                    let cos = (band as f32 + (i as f32 + 0.5) / steps as f32) / BANDS as f32;
+                    let sin = (1.0 - cos * cos).sqrt();
+                    let direction = basis.local(Vec3 { x: sin, y: 0.0, z: cos });
+                    2.0 * PI * material.pdf(&ray, &hit, direction) / (BANDS * steps) as f32
+                })
+                .sum();
+            let fraction = count as f32 / n as f32;
+            assert!((fraction - expected).abs() < 5e-3, "{} != {}", fraction, expected);
+        }
+    }
+}
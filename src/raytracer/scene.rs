@@ -0,0 +1,1556 @@
+//! Scene description files.
+//!
+//! A scene is described in TOML: the camera, the render settings, a table of
+//! named materials and a list of objects referring to them by name. For
+//! example:
+//!
+//! ```toml
+//! [camera]
+//! look_from = [13.0, 2.0, 3.0]
+//! look_at = [0.0, 0.0, 0.0]
+//! vert_fov = 20.0
+//! aperture = 0.1
+//! focus_distance = 10.0
+//!
+//! [render]
+//! width = 1200
+//! height = 600
+//! samples = 24
+//!
+//! [materials.ground]
+//! type = "lambertian"
+//! albedo = [0.5, 0.5, 0.5]
+//!
+//! [[objects]]
+//! type = "sphere"
+//! center = [0.0, -1000.0, 0.0]
+//! radius = 1000.0
+//! material = "ground"
+//! ```
+//!
+//! Without a `[background]` table, a simple sky is used; a `type = "color"`
+//! background with a `color` makes for scenes lit only by `light` materials,
+//! and a `gradient` blends from the color `down` to the one `up`. A
+//! `preetham` background is a physically based clear sky, lit by the sun
+//! either in the direction `sun` or where it is at the solar `time` (in hours,
+//! from 0 to 24) on the `day` of the year (172, midsummer, by default) at the
+//! `latitude` (45 degrees north by default). The sun rises towards +x, the
+//! east, passes to the south, +z, and sets towards -x. The sky's `turbidity`,
+//! from about 2 for clear air to 10 for haze, defaults to 2.5:
+//!
+//! ```toml
+//! [background]
+//! type = "preetham"
+//! time = 17.5
+//! turbidity = 3.0
+//! ```
+//!
+//! Objects may also be triangles, spheres moving from `center0` at `time0`
+//! to `center1` at `time1` (blurred while the camera's shutter, open from
+//! `shutter_open` to `shutter_close`, catches them moving), or models loaded
+//! from OBJ files (whose paths are relative to the scene file) that use their
+//! own MTL materials.
+//!
+//! Flat floors and walls are best made of an infinite `plane` through a
+//! `point` with a `normal`, or of axis-aligned rectangles, such as an
+//! `xz_rect` spanning the intervals `x` and `z` at height `y`, both seen from
+//! either side. A `cuboid` is a box between the corners `min` and `max`:
+//!
+//! ```toml
+//! [[objects]]
+//! type = "plane"
+//! point = [0.0, 0.0, 0.0]
+//! normal = [0.0, 1.0, 0.0]
+//! material = "ground"
+//!
+//! [[objects]]
+//! type = "xz_rect"
+//! x = [-0.5, 0.5]
+//! z = [-0.5, 0.5]
+//! y = 2.0
+//! material = "light"
+//!
+//! [[objects]]
+//! type = "cuboid"
+//! min = [-1.0, 0.0, -1.0]
+//! max = [1.0, 1.0, 1.0]
+//! material = "ground"
+//! ```
+//!
+//! There are also the analytic shapes `cylinder` and `cone`, standing on the
+//! circle of `radius` around their `center` and `height` tall (and closed if
+//! `capped` is set), flat `disk`s and `annulus` rings around a `center`,
+//! facing their `normal`, and the `torus` around the y axis through its
+//! `center`, with a `major_radius` and a `minor_radius`. Shapes standing along
+//! the y axis are turned other ways with a `transform`:
+//!
+//! ```toml
+//! [[objects]]
+//! type = "transform"
+//! rotate = [90.0, 0.0, 0.0]
+//!
+//! [objects.object]
+//! type = "torus"
+//! center = [0.0, 0.0, 0.0]
+//! major_radius = 1.0
+//! minor_radius = 0.25
+//! material = "gold"
+//! ```
+//!
+//! Any object can be placed with a `transform`, which scales, rotates (by
+//! angles in degrees around the x, y and z axes, in that order) and then
+//! translates the `object` nested in it:
+//!
+//! ```toml
+//! [[objects]]
+//! type = "transform"
+//! translate = [0.0, 1.0, 0.0]
+//! rotate = [0.0, 45.0, 0.0]
+//! scale = [1.0, 2.0, 1.0]
+//!
+//! [objects.object]
+//! type = "obj"
+//! path = "teapot.obj"
+//! ```
+//!
+//! Closed objects can be combined into a `union`, an `intersection` or a
+//! `difference`, which cuts its `right` object away from its `left` one:
+//!
+//! ```toml
+//! [[objects]]
+//! type = "difference"
+//!
+//! [objects.left]
+//! type = "cuboid"
+//! min = [-1.0, 0.0, -1.0]
+//! max = [1.0, 1.0, 1.0]
+//! material = "ground"
+//!
+//! [objects.right]
+//! type = "sphere"
+//! center = [0.0, 1.0, 0.0]
+//! radius = 0.8
+//! material = "ground"
+//! ```
+//!
+//! Fog and smoke are made of a `constant_medium`, which fills its closed
+//! `boundary` object with a `density`, and scatters light by its `material`,
+//! usually an `isotropic` one, which scatters light evenly in all directions.
+//! Real fog and clouds scatter more of it forwards, which a `henyey_greenstein`
+//! material does by its `g`, between -1 and 1: positive values scatter light
+//! forwards, negative ones backwards, and zero evenly:
+//!
+//! ```toml
+//! [materials.fog]
+//! type = "henyey_greenstein"
+//! albedo = [0.9, 0.9, 0.9]
+//! g = 0.7
+//!
+//! [[objects]]
+//! type = "constant_medium"
+//! density = 0.5
+//! material = "fog"
+//!
+//! [objects.boundary]
+//! type = "sphere"
+//! center = [0.0, 1.0, 0.0]
+//! radius = 1.0
+//! material = "fog"
+//! ```
+//!
+//! A `grid_medium` varies in density as given by a grid loaded from a file
+//! (in the formats described in the `grid` module), which is stretched across
+//! the box from `min` to `max` and whose values are scaled by `density`. An
+//! `emission` color makes it glow, and like other colors it may be a
+//! texture, whose `u` coordinate is the relative density where it is looked
+//! up:
+//!
+//! ```toml
+//! [[objects]]
+//! type = "grid_medium"
+//! path = "explosion.grid"
+//! min = [-1.0, 0.0, -1.0]
+//! max = [1.0, 2.0, 1.0]
+//! density = 20.0
+//! material = "smoke"
+//! emission = [4.0, 1.5, 0.3]
+//! ```
+//!
+//! Geometry placed many times, such as a model, is better described once in
+//! the `geometry` table and placed by `instance` objects, which share it
+//! rather than each keeping a copy. Instances take the same transformation as
+//! `transform` objects, and may replace the geometry's materials:
+//!
+//! ```toml
+//! [geometry.teapot]
+//! type = "obj"
+//! path = "teapot.obj"
+//!
+//! [[objects]]
+//! type = "instance"
+//! geometry = "teapot"
+//! translate = [2.0, 0.0, 0.0]
+//! material = "gold"
+//! ```
+//!
+//! Wherever a material takes a color, the name of a texture may be given
+//! instead. Textures are described in a table of their own, and are either
+//! checkerboards, images, or `marble`, `wood` and `clouds` patterns generated
+//! from noise, which blend between a `low` and a `high` color. The colors of
+//! textures may themselves be textures:
+//!
+//! ```toml
+//! [textures.checker]
+//! type = "checker"
+//! odd = [0.2, 0.3, 0.1]
+//! even = [0.9, 0.9, 0.9]
+//! scale = 0.3
+//!
+//! [textures.earth]
+//! type = "image"
+//! path = "earth.png"
+//!
+//! [textures.marble]
+//! type = "marble"
+//! low = [0.9, 0.9, 0.85]
+//! high = [0.2, 0.2, 0.25]
+//! scale = 4.0
+//! turbulence = 10.0
+//! octaves = 7
+//! seed = 1
+//!
+//! [materials.ground]
+//! type = "lambertian"
+//! albedo = "checker"
+//! ```
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use raytracer::aabb::Aabb;
+use raytracer::background::*;
+use raytracer::bvh::Bvh;
+use raytracer::camera::*;
+use raytracer::csg::*;
+use raytracer::grid::DensityGrid;
+use raytracer::hit::Hit;
+use raytracer::instance::Instance;
+use raytracer::mat4::Mat4;
+use raytracer::medium::{ConstantMedium, GridMedium};
+use raytracer::obj::load_obj;
+use raytracer::perlin::Perlin;
+use raytracer::phase::{HenyeyGreenstein, Isotropic};
+use raytracer::plane::Plane;
+use raytracer::quadric::*;
+use raytracer::rect::*;
+use raytracer::render::RenderSettings;
+use raytracer::scatter::*;
+use raytracer::sphere::*;
+use raytracer::texture::*;
+use raytracer::torus::Torus;
+use raytracer::transform::Transformed;
+use raytracer::triangle::Triangle;
+use raytracer::vec3::*;
+
+#[derive(Debug)]
+pub struct SceneError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl Error for SceneError {}
+
+pub struct Scene {
+    /// The camera's aspect ratio matches the resolution in `settings`, and
+    /// should be updated if that is changed.
+    pub camera: CameraInfo,
+    pub settings: RenderSettings,
+    pub background: Box<dyn Background>,
+    pub objects: Vec<Box<dyn Hit>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    camera: CameraDesc,
+    #[serde(default)]
+    render: RenderSettings,
+    #[serde(default)]
+    background: BackgroundDesc,
+    #[serde(default)]
+    textures: BTreeMap<String, TextureDesc>,
+    #[serde(default)]
+    materials: BTreeMap<String, MaterialDesc>,
+    #[serde(default)]
+    geometry: BTreeMap<String, ObjectDesc>,
+    #[serde(default)]
+    objects: Vec<ObjectDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    look_from: [f32; 3],
+    look_at: [f32; 3],
+    #[serde(default = "default_view_up")]
+    view_up: [f32; 3],
+    vert_fov: f32,
+    #[serde(default)]
+    aperture: f32,
+    /// Defaults to the distance between `look_from` and `look_at`.
+    focus_distance: Option<f32>,
+    #[serde(default)]
+    shutter_open: f32,
+    #[serde(default)]
+    shutter_close: f32,
+}
+
+fn default_view_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+#[derive(Default, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum BackgroundDesc {
+    #[default]
+    Sky,
+    Color {
+        color: [f32; 3],
+    },
+    Gradient {
+        down: [f32; 3],
+        up: [f32; 3],
+    },
+    Preetham {
+        sun: Option<[f32; 3]>,
+        time: Option<f32>,
+        #[serde(default = "default_day")]
+        day: f32,
+        #[serde(default = "default_latitude")]
+        latitude: f32,
+        #[serde(default = "default_turbidity")]
+        turbidity: f32,
+    },
+}
+
+/// Midsummer in the northern hemisphere.
+fn default_day() -> f32 {
+    172.0
+}
+
+fn default_latitude() -> f32 {
+    45.0
+}
+
+fn default_turbidity() -> f32 {
+    2.5
+}
+
+/// A color, or the name of a texture giving the color across a surface.
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "expected a color [r, g, b] or the name of a texture")]
+enum ColorDesc {
+    Color([f32; 3]),
+    Texture(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum TextureDesc {
+    Checker {
+        odd: ColorDesc,
+        even: ColorDesc,
+        #[serde(default = "default_scale")]
+        scale: f32,
+    },
+    Image {
+        path: String,
+    },
+    Marble {
+        low: ColorDesc,
+        high: ColorDesc,
+        #[serde(default = "default_scale")]
+        scale: f32,
+        #[serde(default = "default_marble_turbulence")]
+        turbulence: f32,
+        #[serde(default = "default_octaves")]
+        octaves: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+    Wood {
+        low: ColorDesc,
+        high: ColorDesc,
+        #[serde(default = "default_scale")]
+        scale: f32,
+        #[serde(default = "default_wood_turbulence")]
+        turbulence: f32,
+        #[serde(default = "default_octaves")]
+        octaves: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+    Clouds {
+        low: ColorDesc,
+        high: ColorDesc,
+        #[serde(default = "default_scale")]
+        scale: f32,
+        #[serde(default = "default_octaves")]
+        octaves: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_marble_turbulence() -> f32 {
+    10.0
+}
+
+fn default_wood_turbulence() -> f32 {
+    0.2
+}
+
+fn default_octaves() -> usize {
+    7
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian {
+        albedo: ColorDesc,
+    },
+    Reflective {
+        albedo: ColorDesc,
+        #[serde(default)]
+        fuzz: f32,
+    },
+    Dielectric {
+        refraction_index: f32,
+    },
+    Light {
+        emit: ColorDesc,
+    },
+    Isotropic {
+        albedo: ColorDesc,
+    },
+    #[serde(rename = "henyey_greenstein")]
+    HenyeyGreenstein {
+        albedo: ColorDesc,
+        g: f32,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    #[serde(rename = "moving_sphere")]
+    MovingSphere {
+        center0: [f32; 3],
+        center1: [f32; 3],
+        #[serde(default)]
+        time0: f32,
+        #[serde(default = "default_time1")]
+        time1: f32,
+        radius: f32,
+        material: String,
+    },
+    Triangle {
+        vertices: [[f32; 3]; 3],
+        material: String,
+    },
+    #[serde(rename = "xy_rect")]
+    XyRect {
+        x: [f32; 2],
+        y: [f32; 2],
+        z: f32,
+        material: String,
+    },
+    #[serde(rename = "xz_rect")]
+    XzRect {
+        x: [f32; 2],
+        z: [f32; 2],
+        y: f32,
+        material: String,
+    },
+    #[serde(rename = "yz_rect")]
+    YzRect {
+        y: [f32; 2],
+        z: [f32; 2],
+        x: f32,
+        material: String,
+    },
+    Cuboid {
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String,
+    },
+    Plane {
+        point: [f32; 3],
+        normal: [f32; 3],
+        material: String,
+    },
+    Cylinder {
+        center: [f32; 3],
+        radius: f32,
+        height: f32,
+        #[serde(default)]
+        capped: bool,
+        material: String,
+    },
+    Cone {
+        center: [f32; 3],
+        radius: f32,
+        height: f32,
+        #[serde(default)]
+        capped: bool,
+        material: String,
+    },
+    Disk {
+        center: [f32; 3],
+        normal: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    Annulus {
+        center: [f32; 3],
+        normal: [f32; 3],
+        inner_radius: f32,
+        outer_radius: f32,
+        material: String,
+    },
+    Torus {
+        center: [f32; 3],
+        major_radius: f32,
+        minor_radius: f32,
+        material: String,
+    },
+    Obj {
+        path: String,
+    },
+    Transform {
+        #[serde(default)]
+        translate: [f32; 3],
+        /// Angles in degrees around the x, y and z axes, in that order.
+        #[serde(default)]
+        rotate: [f32; 3],
+        #[serde(default = "default_scale3")]
+        scale: [f32; 3],
+        object: Box<ObjectDesc>,
+    },
+    Union {
+        left: Box<ObjectDesc>,
+        right: Box<ObjectDesc>,
+    },
+    Intersection {
+        left: Box<ObjectDesc>,
+        right: Box<ObjectDesc>,
+    },
+    /// The `right` object cut away from the `left` one.
+    Difference {
+        left: Box<ObjectDesc>,
+        right: Box<ObjectDesc>,
+    },
+    #[serde(rename = "constant_medium")]
+    ConstantMedium {
+        boundary: Box<ObjectDesc>,
+        density: f32,
+        material: String,
+    },
+    #[serde(rename = "grid_medium")]
+    GridMedium {
+        path: String,
+        min: [f32; 3],
+        max: [f32; 3],
+        #[serde(default = "default_scale")]
+        density: f32,
+        material: String,
+        emission: Option<ColorDesc>,
+    },
+    Instance {
+        geometry: String,
+        #[serde(default)]
+        translate: [f32; 3],
+        #[serde(default)]
+        rotate: [f32; 3],
+        #[serde(default = "default_scale3")]
+        scale: [f32; 3],
+        /// Replaces the geometry's materials if given.
+        material: Option<String>,
+    },
+}
+
+fn default_scale3() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_time1() -> f32 {
+    1.0
+}
+
+fn vec3(v: [f32; 3]) -> Vec3 {
+    Vec3 { x: v[0], y: v[1], z: v[2] }
+}
+
+fn check_finite(what: &str, v: &[f32]) -> Result<(), String> {
+    if v.iter().all(|x| x.is_finite()) {
+        Ok(())
+    } else {
+        Err(format!("{} must be finite", what))
+    }
+}
+
+fn check_positive(what: &str, x: f32) -> Result<(), String> {
+    if x > 0.0 && x.is_finite() {
+        Ok(())
+    } else {
+        Err(format!("{} must be positive, got {}", what, x))
+    }
+}
+
+/// Checks that the interval `v` along `axis` is finite and not empty.
+fn check_extent(axis: &str, v: [f32; 2]) -> Result<(), String> {
+    check_finite(axis, &v)?;
+    if v[0] == v[1] {
+        Err(format!("{} must span a non-empty interval, got {:?}", axis, v))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_normal(normal: [f32; 3]) -> Result<(), String> {
+    check_finite("normal", &normal)?;
+    if vec3(normal).squared_len() > 0.0 {
+        Ok(())
+    } else {
+        Err(String::from("normal must not be zero"))
+    }
+}
+
+fn check_color(what: &str, v: [f32; 3]) -> Result<(), String> {
+    check_finite(what, &v)?;
+    if v.iter().all(|&x| x >= 0.0) {
+        Ok(())
+    } else {
+        Err(format!("{} must not be negative, got {:?}", what, v))
+    }
+}
+
+impl BackgroundDesc {
+    fn to_background(&self) -> Result<Box<dyn Background>, String> {
+        Ok(match *self {
+            BackgroundDesc::Sky => Box::new(GradientBackground::sky()),
+            BackgroundDesc::Color { color } => {
+                check_color("color", color)?;
+                Box::new(SolidBackground { color: vec3(color) })
+            }
+            BackgroundDesc::Gradient { down, up } => {
+                check_color("down", down)?;
+                check_color("up", up)?;
+                Box::new(GradientBackground {
+                    down: vec3(down),
+                    up: vec3(up),
+                })
+            }
+            BackgroundDesc::Preetham {
+                sun,
+                time,
+                day,
+                latitude,
+                turbidity,
+            } => {
+                let sun = match (sun, time) {
+                    (Some(sun), None) => {
+                        check_finite("sun", &sun)?;
+                        if sun == [0.0; 3] {
+                            return Err(String::from("sun must not be zero"));
+                        }
+                        vec3(sun)
+                    }
+                    (None, Some(time)) => {
+                        if !(0.0..=24.0).contains(&time) {
+                            return Err(format!("time must be between 0 and 24, got {}", time));
+                        }
+                        if !(1.0..=366.0).contains(&day) {
+                            return Err(format!("day must be between 1 and 366, got {}", day));
+                        }
+                        if !(-90.0..=90.0).contains(&latitude) {
+                            return Err(format!(
+                                "latitude must be between -90 and 90, got {}",
+                                latitude
+                            ));
+                        }
+                        sun_direction(time, day, latitude)
+                    }
+                    _ => return Err(String::from("exactly one of sun and time must be given")),
+                };
+                if !(1.7..=10.0).contains(&turbidity) {
+                    return Err(format!(
+                        "turbidity must be between 1.7 and 10, got {}",
+                        turbidity
+                    ));
+                }
+                Box::new(PreethamSky::new(sun, turbidity))
+            }
+        })
+    }
+}
+
+impl CameraDesc {
+    fn to_camera_info(&self, settings: &RenderSettings) -> Result<CameraInfo, String> {
+        check_finite("look_from", &self.look_from)?;
+        check_finite("look_at", &self.look_at)?;
+        check_finite("view_up", &self.view_up)?;
+        let look_from = vec3(self.look_from);
+        let look_at = vec3(self.look_at);
+        let view_up = vec3(self.view_up);
+        if look_from == look_at {
+            return Err(String::from("look_from and look_at must differ"));
+        }
+        if cross(view_up, look_from - look_at).squared_len() == 0.0 {
+            return Err(String::from("view_up must not be parallel to the view direction"));
+        }
+        if !(self.vert_fov > 0.0 && self.vert_fov < 180.0) {
+            return Err(format!("vert_fov must be between 0 and 180, got {}", self.vert_fov));
+        }
+        if !(self.aperture >= 0.0 && self.aperture.is_finite()) {
+            return Err(format!("aperture must not be negative, got {}", self.aperture));
+        }
+        let focus_distance = self.focus_distance.unwrap_or_else(|| (look_from - look_at).len());
+        if !(focus_distance > 0.0 && focus_distance.is_finite()) {
+            return Err(format!("focus_distance must be positive, got {}", focus_distance));
+        }
+        check_finite("shutter_open", &[self.shutter_open])?;
+        check_finite("shutter_close", &[self.shutter_close])?;
+        if self.shutter_close < self.shutter_open {
+            return Err(format!(
+                "shutter_close must not be before shutter_open, got {} and {}",
+                self.shutter_close, self.shutter_open
+            ));
+        }
+        Ok(CameraInfo {
+            look_from,
+            look_at,
+            view_up,
+            vert_fov: self.vert_fov,
+            aspect: settings.width as f32 / settings.height as f32,
+            aperture: self.aperture,
+            focus_distance,
+            shutter_open: self.shutter_open,
+            shutter_close: self.shutter_close,
+        })
+    }
+}
+
+impl TextureDesc {
+    fn validate(&self) -> Result<(), String> {
+        let (scale, turbulence, octaves) = match *self {
+            TextureDesc::Checker { scale, .. } => (scale, 0.0, 1),
+            TextureDesc::Image { .. } => return Ok(()),
+            TextureDesc::Marble { scale, turbulence, octaves, .. }
+            | TextureDesc::Wood { scale, turbulence, octaves, .. } => (scale, turbulence, octaves),
+            TextureDesc::Clouds { scale, octaves, .. } => (scale, 0.0, octaves),
+        };
+        if !(scale > 0.0 && scale.is_finite()) {
+            return Err(format!("scale must be positive, got {}", scale));
+        }
+        if !(turbulence >= 0.0 && turbulence.is_finite()) {
+            return Err(format!("turbulence must not be negative, got {}", turbulence));
+        }
+        if octaves == 0 {
+            return Err(String::from("octaves must be positive"));
+        }
+        Ok(())
+    }
+}
+
+impl MaterialDesc {
+    fn validate(&self) -> Result<(), String> {
+        match *self {
+            MaterialDesc::Reflective { fuzz, .. } => {
+                if (0.0..=1.0).contains(&fuzz) {
+                    Ok(())
+                } else {
+                    Err(format!("fuzz must be between 0 and 1, got {}", fuzz))
+                }
+            }
+            MaterialDesc::Dielectric { refraction_index } => {
+                if refraction_index > 0.0 && refraction_index.is_finite() {
+                    Ok(())
+                } else {
+                    Err(format!("refraction_index must be positive, got {}", refraction_index))
+                }
+            }
+            MaterialDesc::HenyeyGreenstein { g, .. } => {
+                if g > -1.0 && g < 1.0 {
+                    Ok(())
+                } else {
+                    Err(format!("g must be between -1 and 1, exclusive, got {}", g))
+                }
+            }
+            MaterialDesc::Lambertian { .. }
+            | MaterialDesc::Light { .. }
+            | MaterialDesc::Isotropic { .. } => Ok(()),
+        }
+    }
+}
+
+/// Builds the parts of a scene that objects refer to by name: the materials
+/// and their textures, and the geometry of instances, as well as the
+/// density grids of media, which are referred to by path. Each of them is
+/// built only once and shared by all the objects using it.
+struct SceneBuilder<'a> {
+    desc: &'a SceneDesc,
+    /// The directory relative to which the paths in the scene are resolved.
+    dir: &'a Path,
+    images: HashMap<&'a str, ImageTexture>,
+    textures: RefCell<HashMap<String, Arc<dyn Texture>>>,
+    materials: HashMap<&'a str, Arc<dyn Scatter>>,
+    geometry: RefCell<HashMap<String, Arc<dyn Hit>>>,
+    /// The names of the geometry being built, in order of nesting, to detect
+    /// geometry that contains itself.
+    geometry_stack: RefCell<Vec<String>>,
+    grids: RefCell<HashMap<PathBuf, Arc<DensityGrid>>>,
+}
+
+impl<'a> SceneBuilder<'a> {
+    /// Builds the texture described by `color`, which is named `what` in
+    /// errors, and is nested `depth` levels deep in other textures. Named
+    /// textures are built on first use.
+    fn texture(
+        &self,
+        what: &str,
+        color: &ColorDesc,
+        depth: usize,
+    ) -> Result<Box<dyn Texture>, String> {
+        let name = match *color {
+            ColorDesc::Color(color) => {
+                check_color(what, color)?;
+                return Ok(Box::new(SolidColor { color: vec3(color) }));
+            }
+            ColorDesc::Texture(ref name) => name,
+        };
+        if let Some(texture) = self.textures.borrow().get(name) {
+            return Ok(Box::new(Arc::clone(texture)));
+        }
+        // A texture can only be nested as deep as there are textures, unless
+        // it refers back to itself.
+        if depth > self.desc.textures.len() {
+            return Err(format!("texture '{}' refers to itself", name));
+        }
+        let texture: Arc<dyn Texture> = match self.desc.textures.get(name) {
+            Some(&TextureDesc::Checker { ref odd, ref even, scale }) => Arc::new(Checker {
+                odd: self.texture("odd", odd, depth + 1)?,
+                even: self.texture("even", even, depth + 1)?,
+                scale,
+            }),
+            Some(&TextureDesc::Image { .. }) => Arc::new(self.images[name.as_str()].clone()),
+            Some(&TextureDesc::Marble { ref low, ref high, scale, turbulence, octaves, seed }) => {
+                Arc::new(Marble {
+                    noise: Perlin::new(seed),
+                    low: self.texture("low", low, depth + 1)?,
+                    high: self.texture("high", high, depth + 1)?,
+                    scale,
+                    turbulence,
+                    octaves,
+                })
+            }
+            Some(&TextureDesc::Wood { ref low, ref high, scale, turbulence, octaves, seed }) => {
+                Arc::new(Wood {
+                    noise: Perlin::new(seed),
+                    low: self.texture("low", low, depth + 1)?,
+                    high: self.texture("high", high, depth + 1)?,
+                    scale,
+                    turbulence,
+                    octaves,
+                })
+            }
+            Some(&TextureDesc::Clouds { ref low, ref high, scale, octaves, seed }) => {
+                Arc::new(Clouds {
+                    noise: Perlin::new(seed),
+                    low: self.texture("low", low, depth + 1)?,
+                    high: self.texture("high", high, depth + 1)?,
+                    scale,
+                    octaves,
+                })
+            }
+            None => return Err(format!("unknown texture '{}'", name)),
+        };
+        self.textures
+            .borrow_mut()
+            .insert(name.clone(), Arc::clone(&texture));
+        Ok(Box::new(texture))
+    }
+
+    fn material(&self, name: &str) -> Result<Arc<dyn Scatter>, String> {
+        match self.materials.get(name) {
+            Some(material) => Ok(Arc::clone(material)),
+            None => Err(format!("unknown material '{}'", name)),
+        }
+    }
+
+    fn build_material(&self, name: &str) -> Result<Arc<dyn Scatter>, String> {
+        let material = match self.desc.materials.get(name) {
+            Some(material) => material,
+            None => return Err(format!("unknown material '{}'", name)),
+        };
+        Ok(match *material {
+            MaterialDesc::Lambertian { ref albedo } => Arc::new(Lambertian {
+                albedo: self.texture("albedo", albedo, 0)?,
+            }),
+            MaterialDesc::Reflective { ref albedo, fuzz } => Arc::new(Reflective {
+                albedo: self.texture("albedo", albedo, 0)?,
+                fuzz,
+            }),
+            MaterialDesc::Dielectric { refraction_index } => {
+                Arc::new(Dielectric { refraction_index })
+            }
+            MaterialDesc::Light { ref emit } => Arc::new(DiffuseLight {
+                emit: self.texture("emit", emit, 0)?,
+            }),
+            MaterialDesc::Isotropic { ref albedo } => Arc::new(Volume {
+                albedo: self.texture("albedo", albedo, 0)?,
+                phase_function: Box::new(Isotropic),
+            }),
+            MaterialDesc::HenyeyGreenstein { ref albedo, g } => Arc::new(Volume {
+                albedo: self.texture("albedo", albedo, 0)?,
+                phase_function: Box::new(HenyeyGreenstein { g }),
+            }),
+        })
+    }
+
+    /// Returns the geometry called `name`, building it on first use. If it
+    /// consists of several objects, they are grouped in a hierarchy of their
+    /// own.
+    fn geometry(&self, name: &str) -> Result<Arc<dyn Hit>, String> {
+        if let Some(geometry) = self.geometry.borrow().get(name) {
+            return Ok(Arc::clone(geometry));
+        }
+        let desc = match self.desc.geometry.get(name) {
+            Some(desc) => desc,
+            None => return Err(format!("unknown geometry '{}'", name)),
+        };
+        if self.geometry_stack.borrow().iter().any(|n| n == name) {
+            return Err(format!("geometry '{}' contains itself", name));
+        }
+
+        self.geometry_stack.borrow_mut().push(name.to_string());
+        let mut objects = Vec::new();
+        let result = desc.build(self, &mut objects);
+        self.geometry_stack.borrow_mut().pop();
+        result.map_err(|e| format!("geometry.{}: {}", name, e))?;
+
+        let geometry: Arc<dyn Hit> = if objects.len() == 1 {
+            Arc::from(objects.pop().unwrap())
+        } else {
+            Arc::new(Bvh::new(objects))
+        };
+        self.geometry
+            .borrow_mut()
+            .insert(name.to_string(), Arc::clone(&geometry));
+        Ok(geometry)
+    }
+
+    /// Returns the density grid in the file at `path`, relative to the scene,
+    /// loading it on first use.
+    fn grid(&self, path: &str) -> Result<Arc<DensityGrid>, String> {
+        let path = self.dir.join(path);
+        if let Some(grid) = self.grids.borrow().get(&path) {
+            return Ok(Arc::clone(grid));
+        }
+        let grid = Arc::new(DensityGrid::load(&path).map_err(|e| e.to_string())?);
+        self.grids.borrow_mut().insert(path, Arc::clone(&grid));
+        Ok(grid)
+    }
+}
+
+/// Returns the transformation that scales, rotates around the x, y and z axes
+/// by angles in degrees, and then translates.
+fn transform(translate: [f32; 3], rotate: [f32; 3], scale: [f32; 3]) -> Result<Mat4, String> {
+    check_finite("translate", &translate)?;
+    check_finite("rotate", &rotate)?;
+    check_finite("scale", &scale)?;
+    if scale.contains(&0.0) {
+        return Err(format!("scale must not be zero, got {:?}", scale));
+    }
+    let axis = |x, y, z| Vec3 { x, y, z };
+    let matrix = Mat4::translation(vec3(translate))
+        * Mat4::rotation(axis(0.0, 0.0, 1.0), rotate[2])
+        * Mat4::rotation(axis(0.0, 1.0, 0.0), rotate[1])
+        * Mat4::rotation(axis(1.0, 0.0, 0.0), rotate[0])
+        * Mat4::scaling(vec3(scale));
+    // Scales too small to be told from zero leave the transform singular, which
+    // `Transformed` cannot undo.
+    if matrix.inverse().is_none() {
+        return Err(format!("transform is not invertible, with scale {:?}", scale));
+    }
+    Ok(matrix)
+}
+
+impl ObjectDesc {
+    /// Builds the objects described as a single one, grouping them if there
+    /// are many. `what` names the object in errors.
+    fn build_one(&self, scene: &SceneBuilder, what: &str) -> Result<Box<dyn Hit>, String> {
+        let mut objects = Vec::new();
+        self.build(scene, &mut objects)
+            .map_err(|e| format!("{}: {}", what, e))?;
+        if objects.len() == 1 {
+            Ok(objects.pop().unwrap())
+        } else {
+            Ok(Box::new(objects))
+        }
+    }
+
+    /// Adds the objects described to `objects`, which is usually one, but may
+    /// be many for models.
+    fn build(&self, scene: &SceneBuilder, objects: &mut Vec<Box<dyn Hit>>) -> Result<(), String> {
+        match *self {
+            ObjectDesc::Sphere { center, radius, ref material } => {
+                check_finite("center", &center)?;
+                check_positive("radius", radius)?;
+                objects.push(Box::new(Sphere {
+                    center: vec3(center),
+                    radius,
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                ref material,
+            } => {
+                check_finite("center0", &center0)?;
+                check_finite("center1", &center1)?;
+                check_finite("time0", &[time0])?;
+                check_finite("time1", &[time1])?;
+                if time1 <= time0 {
+                    return Err(format!(
+                        "time1 must be after time0, got {} and {}",
+                        time1, time0
+                    ));
+                }
+                check_positive("radius", radius)?;
+                objects.push(Box::new(MovingSphere {
+                    center0: vec3(center0),
+                    center1: vec3(center1),
+                    time0,
+                    time1,
+                    radius,
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::Triangle { vertices, ref material } => {
+                for v in vertices.iter() {
+                    check_finite("vertices", v)?;
+                }
+                objects.push(Box::new(Triangle {
+                    v0: vec3(vertices[0]),
+                    v1: vec3(vertices[1]),
+                    v2: vec3(vertices[2]),
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::XyRect { x, y, z, ref material } => {
+                check_extent("x", x)?;
+                check_extent("y", y)?;
+                check_finite("z", &[z])?;
+                objects.push(Box::new(XyRect {
+                    x0: x[0],
+                    x1: x[1],
+                    y0: y[0],
+                    y1: y[1],
+                    k: z,
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::XzRect { x, z, y, ref material } => {
+                check_extent("x", x)?;
+                check_extent("z", z)?;
+                check_finite("y", &[y])?;
+                objects.push(Box::new(XzRect {
+                    x0: x[0],
+                    x1: x[1],
+                    z0: z[0],
+                    z1: z[1],
+                    k: y,
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::YzRect { y, z, x, ref material } => {
+                check_extent("y", y)?;
+                check_extent("z", z)?;
+                check_finite("x", &[x])?;
+                objects.push(Box::new(YzRect {
+                    y0: y[0],
+                    y1: y[1],
+                    z0: z[0],
+                    z1: z[1],
+                    k: x,
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::Cuboid { min, max, ref material } => {
+                check_finite("min", &min)?;
+                check_finite("max", &max)?;
+                if (0..3).any(|i| min[i] == max[i]) {
+                    return Err(format!(
+                        "min and max must differ along every axis, got {:?} and {:?}",
+                        min, max
+                    ));
+                }
+                objects.push(Box::new(Cuboid::new(
+                    vec3(min),
+                    vec3(max),
+                    scene.material(material)?,
+                )));
+            }
+            ObjectDesc::Plane { point, normal, ref material } => {
+                check_finite("point", &point)?;
+                check_normal(normal)?;
+                objects.push(Box::new(Plane {
+                    point: vec3(point),
+                    normal: vec3(normal),
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::Cylinder { center, radius, height, capped, ref material } => {
+                check_finite("center", &center)?;
+                check_positive("radius", radius)?;
+                check_positive("height", height)?;
+                objects.push(Box::new(Cylinder {
+                    center: vec3(center),
+                    radius,
+                    height,
+                    capped,
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::Cone { center, radius, height, capped, ref material } => {
+                check_finite("center", &center)?;
+                check_positive("radius", radius)?;
+                check_positive("height", height)?;
+                objects.push(Box::new(Cone {
+                    center: vec3(center),
+                    radius,
+                    height,
+                    capped,
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::Disk { center, normal, radius, ref material } => {
+                check_finite("center", &center)?;
+                check_normal(normal)?;
+                check_positive("radius", radius)?;
+                objects.push(Box::new(Disk {
+                    center: vec3(center),
+                    normal: vec3(normal),
+                    radius,
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::Annulus {
+                center,
+                normal,
+                inner_radius,
+                outer_radius,
+                ref material,
+            } => {
+                check_finite("center", &center)?;
+                check_normal(normal)?;
+                check_positive("inner_radius", inner_radius)?;
+                check_positive("outer_radius", outer_radius)?;
+                if outer_radius <= inner_radius {
+                    return Err(format!(
+                        "outer_radius must be larger than inner_radius, got {} and {}",
+                        outer_radius, inner_radius
+                    ));
+                }
+                objects.push(Box::new(Annulus {
+                    center: vec3(center),
+                    normal: vec3(normal),
+                    inner_radius,
+                    outer_radius,
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::Torus { center, major_radius, minor_radius, ref material } => {
+                check_finite("center", &center)?;
+                check_positive("major_radius", major_radius)?;
+                check_positive("minor_radius", minor_radius)?;
+                objects.push(Box::new(Torus {
+                    center: vec3(center),
+                    major_radius,
+                    minor_radius,
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::Obj { ref path } => {
+                let groups = load_obj(scene.dir.join(path)).map_err(|e| e.to_string())?;
+                for group in groups {
+                    objects.push(Box::new(group.mesh));
+                }
+            }
+            ObjectDesc::Transform { translate, rotate, scale, ref object } => {
+                let transform = transform(translate, rotate, scale)?;
+                let mut inner = Vec::new();
+                object
+                    .build(scene, &mut inner)
+                    .map_err(|e| format!("object: {}", e))?;
+                for object in inner {
+                    objects.push(Box::new(Transformed::new(object, transform)));
+                }
+            }
+            ObjectDesc::Union { ref left, ref right } => {
+                objects.push(Box::new(Union {
+                    left: left.build_one(scene, "left")?,
+                    right: right.build_one(scene, "right")?,
+                }));
+            }
+            ObjectDesc::Intersection { ref left, ref right } => {
+                objects.push(Box::new(Intersection {
+                    left: left.build_one(scene, "left")?,
+                    right: right.build_one(scene, "right")?,
+                }));
+            }
+            ObjectDesc::Difference { ref left, ref right } => {
+                objects.push(Box::new(Difference {
+                    left: left.build_one(scene, "left")?,
+                    right: right.build_one(scene, "right")?,
+                }));
+            }
+            ObjectDesc::ConstantMedium { ref boundary, density, ref material } => {
+                check_positive("density", density)?;
+                objects.push(Box::new(ConstantMedium {
+                    boundary: boundary.build_one(scene, "boundary")?,
+                    density,
+                    material: scene.material(material)?,
+                }));
+            }
+            ObjectDesc::GridMedium {
+                ref path,
+                min,
+                max,
+                density,
+                ref material,
+                ref emission,
+            } => {
+                check_finite("min", &min)?;
+                check_finite("max", &max)?;
+                if (0..3).any(|i| min[i] >= max[i]) {
+                    return Err(format!(
+                        "max must exceed min along every axis, got {:?} and {:?}",
+                        max, min
+                    ));
+                }
+                check_positive("density", density)?;
+                let grid = scene.grid(path)?;
+                let emission = match *emission {
+                    Some(ref emission) => Some(scene.texture("emission", emission, 0)?),
+                    None => None,
+                };
+                objects.push(Box::new(GridMedium::new(
+                    grid,
+                    Aabb { min: vec3(min), max: vec3(max) },
+                    density,
+                    scene.material(material)?,
+                    emission,
+                )));
+            }
+            ObjectDesc::Instance {
+                ref geometry,
+                translate,
+                rotate,
+                scale,
+                ref material,
+            } => {
+                let transform = transform(translate, rotate, scale)?;
+                let material = match *material {
+                    Some(ref material) => Some(scene.material(material)?),
+                    None => None,
+                };
+                objects.push(Box::new(Instance::new(
+                    scene.geometry(geometry)?,
+                    transform,
+                    material,
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SceneDesc {
+    fn to_scene(&self, dir: &Path) -> Result<Scene, String> {
+        let settings = self.render;
+        if settings.width == 0 || settings.height == 0 {
+            return Err(format!(
+                "render: resolution must be positive, got {}x{}",
+                settings.width, settings.height
+            ));
+        }
+        if settings.n_samples == 0 {
+            return Err(String::from("render: samples must be positive"));
+        }
+        let camera = self
+            .camera
+            .to_camera_info(&settings)
+            .map_err(|e| format!("camera: {}", e))?;
+        let mut builder = SceneBuilder {
+            desc: self,
+            dir,
+            images: HashMap::new(),
+            textures: RefCell::new(HashMap::new()),
+            materials: HashMap::new(),
+            geometry: RefCell::new(HashMap::new()),
+            geometry_stack: RefCell::new(Vec::new()),
+            grids: RefCell::new(HashMap::new()),
+        };
+        for (name, texture) in self.textures.iter() {
+            let context = |e: String| format!("textures.{}: {}", name, e);
+            texture.validate().map_err(context)?;
+            if let TextureDesc::Image { ref path } = *texture {
+                let image = ImageTexture::load(dir.join(path))
+                    .map_err(|e| context(format!("could not load '{}': {}", path, e)))?;
+                builder.images.insert(name, image);
+            }
+        }
+        // Build every texture, material and piece of geometry up front to
+        // report their errors even if they are unused.
+        for name in self.textures.keys() {
+            builder
+                .texture("color", &ColorDesc::Texture(name.clone()), 0)
+                .map_err(|e| format!("textures.{}: {}", name, e))?;
+        }
+        for (name, material) in self.materials.iter() {
+            let material = material
+                .validate()
+                .and_then(|_| builder.build_material(name))
+                .map_err(|e| format!("materials.{}: {}", name, e))?;
+            builder.materials.insert(name, material);
+        }
+        for name in self.geometry.keys() {
+            builder.geometry(name)?;
+        }
+
+        let mut objects: Vec<Box<dyn Hit>> = Vec::with_capacity(self.objects.len());
+        for (i, object) in self.objects.iter().enumerate() {
+            object
+                .build(&builder, &mut objects)
+                .map_err(|e| format!("objects[{}]: {}", i, e))?;
+        }
+
+        let background = self
+            .background
+            .to_background()
+            .map_err(|e| format!("background: {}", e))?;
+
+        Ok(Scene {
+            camera,
+            settings,
+            background,
+            objects,
+        })
+    }
+}
+
+/// Loads the scene described by the file at `path`.
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let error = |message: String| SceneError {
+        path: path.to_path_buf(),
+        message,
+    };
+    let text = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    let desc: SceneDesc = toml::from_str(&text).map_err(|e| error(e.to_string()))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    desc.to_scene(dir).map_err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::*;
+
+    /// The camera, which every scene needs.
+    const CAMERA: &str = "
+        [camera]
+        look_from = [0.0, 0.0, 0.0]
+        look_at = [0.0, 0.0, -1.0]
+        vert_fov = 90.0
+    ";
+
+    /// A directory of scene files for a single test, removed once it is done.
+    struct SceneDir(PathBuf);
+
+    impl SceneDir {
+        fn new(test: &str) -> Self {
+            let dir = env::temp_dir().join(format!("raytracer-scene-{}-{}", test, process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            SceneDir(dir)
+        }
+
+        /// Loads the scene described by `text`, after the camera, expecting it
+        /// to fail, and returns the error message.
+        fn load_error(&self, text: &str) -> String {
+            let path = self.0.join("scene.toml");
+            fs::write(&path, format!("{}\n{}", CAMERA, text)).unwrap();
+            match load_scene(&path) {
+                Ok(_) => panic!("loaded invalid scene:\n{}", text),
+                Err(e) => {
+                    assert_eq!(e.path, path);
+                    e.message
+                }
+            }
+        }
+    }
+
+    impl Drop for SceneDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn example_scenes_load() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+        let mut n_scenes = 0;
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "toml") {
+                if let Err(e) = load_scene(&path) {
+                    panic!("{}", e);
+                }
+                n_scenes += 1;
+            }
+        }
+        assert!(n_scenes > 0);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let dir = SceneDir::new("unknown-keys");
+        let errors = [
+            ("[lights]", "lights"),
+            ("[render]\nwidth = 10\nthreads = 2", "threads"),
+            ("[materials.red]\ntype = \"lambertian\"\ncolor = [1.0, 0.0, 0.0]", "color"),
+            (
+                "[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nradius = 1.0\n\
+                 material = \"red\"\nradious = 1.0",
+                "radious",
+            ),
+        ];
+        for &(text, key) in errors.iter() {
+            let message = dir.load_error(text);
+            assert!(message.contains(&format!("unknown field `{}`", key)), "{}", message);
+        }
+        let message = dir.load_error("[materials.red]\ntype = \"plastic\"");
+        assert!(message.contains("unknown variant `plastic`"), "{}", message);
+    }
+
+    #[test]
+    fn negative_radii_are_rejected() {
+        let dir = SceneDir::new("negative-radii");
+        let material = "[materials.grey]\ntype = \"lambertian\"\nalbedo = [0.5, 0.5, 0.5]\n";
+        let sphere = "[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\n\
+                      material = \"grey\"\n";
+        let text = format!("{}{}radius = 1.0\n{}radius = -0.5", material, sphere, sphere);
+        assert_eq!(dir.load_error(&text), "objects[1]: radius must be positive, got -0.5");
+        assert_eq!(
+            dir.load_error(&format!("{}{}radius = 0.0", material, sphere)),
+            "objects[0]: radius must be positive, got 0"
+        );
+    }
+
+    #[test]
+    fn fuzz_outside_zero_to_one_is_rejected() {
+        let dir = SceneDir::new("fuzz");
+        for &fuzz in ["-0.1", "1.5"].iter() {
+            let text = format!(
+                "[materials.metal]\ntype = \"reflective\"\nalbedo = [0.8, 0.8, 0.8]\nfuzz = {}",
+                fuzz
+            );
+            assert_eq!(
+                dir.load_error(&text),
+                format!("materials.metal: fuzz must be between 0 and 1, got {}", fuzz)
+            );
+        }
+    }
+
+    #[test]
+    fn non_positive_refraction_indices_are_rejected() {
+        let dir = SceneDir::new("refraction-index");
+        for &(index, shown) in [("0.0", "0"), ("-1.5", "-1.5")].iter() {
+            let text = format!(
+                "[materials.glass]\ntype = \"dielectric\"\nrefraction_index = {}",
+                index
+            );
+            assert_eq!(
+                dir.load_error(&text),
+                format!("materials.glass: refraction_index must be positive, got {}", shown)
+            );
+        }
+    }
+
+    #[test]
+    fn named_textures_are_built_once() {
+        let text = format!(
+            "{}
+            [textures.marble]
+            type = \"marble\"
+            low = [0.1, 0.1, 0.1]
+            high = [0.9, 0.9, 0.9]
+
+            [textures.checker]
+            type = \"checker\"
+            odd = \"marble\"
+            even = \"marble\"
+            ",
+            CAMERA
+        );
+        let desc: SceneDesc = toml::from_str(&text).unwrap();
+        let builder = SceneBuilder {
+            desc: &desc,
+            dir: Path::new(""),
+            images: HashMap::new(),
+            textures: RefCell::new(HashMap::new()),
+            materials: HashMap::new(),
+            geometry: RefCell::new(HashMap::new()),
+            geometry_stack: RefCell::new(Vec::new()),
+            grids: RefCell::new(HashMap::new()),
+        };
+        let checker = ColorDesc::Texture(String::from("checker"));
+        let _first = builder.texture("albedo", &checker, 0).unwrap();
+        let _second = builder.texture("albedo", &checker, 0).unwrap();
+        let textures = builder.textures.borrow();
+        assert_eq!(textures.len(), 2);
+        // The marble is shared by both sides of the checker, and the checker by
+        // both the textures built from it.
+        assert_eq!(Arc::strong_count(&textures["marble"]), 3);
+        assert_eq!(Arc::strong_count(&textures["checker"]), 3);
+    }
+}
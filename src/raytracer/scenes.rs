@@ -0,0 +1,336 @@
+//! Scenes built into the renderer, generated in code rather than loaded from
+//! scene files.
+
+use std::sync::Arc;
+
+use raytracer::background::{GradientBackground, SolidBackground};
+use raytracer::camera::CameraInfo;
+use raytracer::hit::Hit;
+use raytracer::mat4::Mat4;
+use raytracer::medium::ConstantMedium;
+use raytracer::phase::Isotropic;
+use raytracer::plane::Plane;
+use raytracer::rect::*;
+use raytracer::render::RenderSettings;
+use raytracer::sampler::Sampler;
+use raytracer::scatter::*;
+use raytracer::scene::Scene;
+use raytracer::sphere::Sphere;
+use raytracer::texture::*;
+use raytracer::transform::Transformed;
+use raytracer::vec3::Vec3;
+
+/// The names of the built-in scenes.
+pub const BUILT_IN_SCENES: [&str; 4] = ["book-cover", "basic", "cornell", "cornell-smoke"];
+
+/// Returns the built-in scene called `name`, rendered with `settings`.
+pub fn built_in_scene(name: &str, settings: RenderSettings) -> Option<Scene> {
+    match name {
+        "book-cover" => Some(book_cover_scene(settings)),
+        "basic" => Some(basic_scene(settings)),
+        "cornell" => Some(cornell_scene(settings)),
+        "cornell-smoke" => Some(cornell_smoke_scene(settings)),
+        _ => None,
+    }
+}
+
+/// The random scene akin to the cover of Ray Tracing in One Weekend.
+pub fn book_cover_scene(settings: RenderSettings) -> Scene {
+    Scene {
+        camera: CameraInfo {
+            look_from: Vec3 { x: 13.0, y: 2.0, z: 3.0 },
+            look_at: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+            view_up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+            vert_fov: 20.0,
+            aspect: settings.width as f32 / settings.height as f32,
+            aperture: 0.1,
+            focus_distance: 10.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        },
+        settings,
+        background: Box::new(GradientBackground::sky()),
+        objects: rand_scene(settings.seed),
+    }
+}
+
+/// The scene from the first chapters of the book, viewed through a camera
+/// like `Camera::axis_aligned`.
+pub fn basic_scene(settings: RenderSettings) -> Scene {
+    Scene {
+        camera: CameraInfo {
+            look_from: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+            look_at: Vec3 { x: 0.0, y: 0.0, z: -1.0 },
+            view_up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+            vert_fov: 90.0,
+            aspect: settings.width as f32 / settings.height as f32,
+            aperture: 0.0,
+            focus_distance: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        },
+        settings,
+        background: Box::new(GradientBackground::sky()),
+        objects: basic_objects(),
+    }
+}
+
+/// A Cornell box style room, with a tall block and a glass ball, lit only by a
+/// square light in its ceiling.
+pub fn cornell_scene(settings: RenderSettings) -> Scene {
+    let mut objects = cornell_room();
+    objects.push(Box::new(Transformed::new(
+        Cuboid::new(
+            Vec3 { x: -0.3, y: -0.6, z: -0.3 },
+            Vec3 { x: 0.3, y: 0.6, z: 0.3 },
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor { color: Vec3 { x: 0.73, y: 0.73, z: 0.73 } }),
+            }),
+        ),
+        Mat4::translation(Vec3 { x: -0.4, y: -0.4, z: -0.35 })
+            * Mat4::rotation(Vec3 { x: 0.0, y: 1.0, z: 0.0 }, 18.0),
+    )));
+    objects.push(Box::new(Sphere {
+        center: Vec3 { x: 0.4, y: -0.6, z: 0.3 },
+        radius: 0.4,
+        material: Arc::new(Dielectric { refraction_index: 1.5 }),
+    }));
+    Scene {
+        camera: cornell_camera(&settings),
+        settings,
+        background: Box::new(SolidBackground {
+            color: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        }),
+        objects,
+    }
+}
+
+/// The Cornell box with blocks of dark smoke and white fog in place of its
+/// block and ball.
+pub fn cornell_smoke_scene(settings: RenderSettings) -> Scene {
+    let mut objects = cornell_room();
+    let medium = |min: Vec3, max: Vec3, angle: f32, density: f32, color: Vec3| -> Box<dyn Hit> {
+        let material: Arc<dyn Scatter> = Arc::new(Volume {
+            albedo: Box::new(SolidColor { color }),
+            phase_function: Box::new(Isotropic),
+        });
+        // The boundary's own material is never seen.
+        let boundary = Transformed::new(
+            Cuboid::new(min, max, Arc::clone(&material)),
+            Mat4::rotation(Vec3 { x: 0.0, y: 1.0, z: 0.0 }, angle),
+        );
+        Box::new(ConstantMedium {
+            boundary,
+            density,
+            material,
+        })
+    };
+    objects.push(medium(
+        Vec3 { x: -0.7, y: -1.0, z: -0.7 },
+        Vec3 { x: -0.1, y: 0.2, z: -0.1 },
+        18.0,
+        4.0,
+        Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+    ));
+    objects.push(medium(
+        Vec3 { x: 0.1, y: -1.0, z: 0.0 },
+        Vec3 { x: 0.7, y: -0.4, z: 0.6 },
+        -15.0,
+        4.0,
+        Vec3 { x: 1.0, y: 1.0, z: 1.0 },
+    ));
+    Scene {
+        camera: cornell_camera(&settings),
+        settings,
+        background: Box::new(SolidBackground {
+            color: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        }),
+        objects,
+    }
+}
+
+fn cornell_camera(settings: &RenderSettings) -> CameraInfo {
+    CameraInfo {
+        look_from: Vec3 { x: 0.0, y: 0.0, z: 3.9 },
+        look_at: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        view_up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+        vert_fov: 40.0,
+        aspect: settings.width as f32 / settings.height as f32,
+        aperture: 0.0,
+        focus_distance: 3.9,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    }
+}
+
+fn basic_objects() -> Vec<Box<dyn Hit>> {
+    vec![
+        Box::new(Sphere {
+            center: Vec3 { x: 0.0, y: 0.0, z: -1.0 },
+            radius: 0.5,
+            material: Arc::new(Lambertian {
+                albedo: Box::new(SolidColor { color: Vec3 { x: 0.8, y: 0.3, z: 0.3 } }),
+            }),
+        }),
+        Box::new(Sphere {
+            center: Vec3 { x: 0.0, y: -100.5, z: -1.0 },
+            radius: 100.0,
+            material: Arc::new(Lambertian {
+                albedo: Box::new(SolidColor { color: Vec3 { x: 0.5, y: 0.5, z: 0.5 } }),
+            }),
+        }),
+        Box::new(Sphere {
+            center: Vec3 { x: 1.0, y: 0.0, z: -1.0 },
+            radius: 0.5,
+            material: Arc::new(Reflective {
+                albedo: Box::new(SolidColor { color: Vec3 { x: 0.4, y: 0.6, z: 0.8 } }),
+                fuzz: 0.9,
+            }),
+        }),
+        //Box::new(Sphere {
+            //center: Vec3 { x: -1.0, y: 0.0, z: -1.0 },
+            //radius: 0.5,
+            //material: Arc::new(Dielectric { refraction_index: 1.5 }),
+        //}),
+        Box::new(Sphere {
+            center: Vec3 { x: -1.0, y: 0.0, z: -1.0 },
+            radius: 0.5,
+            material: Arc::new(Reflective {
+                albedo: Box::new(SolidColor { color: Vec3 { x: 0.8, y: 0.8, z: 0.8 } }),
+                fuzz: 0.3,
+            }),
+        }),
+    ]
+}
+
+/// The walls and light of the Cornell box, which is otherwise empty.
+fn cornell_room() -> Vec<Box<dyn Hit>> {
+    let white: Arc<dyn Scatter> = Arc::new(Lambertian {
+        albedo: Box::new(SolidColor { color: Vec3 { x: 0.73, y: 0.73, z: 0.73 } }),
+    });
+    let red = Arc::new(Lambertian {
+        albedo: Box::new(SolidColor { color: Vec3 { x: 0.65, y: 0.05, z: 0.05 } }),
+    });
+    let green = Arc::new(Lambertian {
+        albedo: Box::new(SolidColor { color: Vec3 { x: 0.12, y: 0.45, z: 0.15 } }),
+    });
+    let light = Arc::new(DiffuseLight {
+        emit: Box::new(SolidColor { color: Vec3 { x: 15.0, y: 15.0, z: 15.0 } }),
+    });
+    // The walls of the box span -1 to 1 along each axis.
+    let yz_wall = |x: f32, material: Arc<dyn Scatter>| -> Box<dyn Hit> {
+        Box::new(YzRect { y0: -1.0, y1: 1.0, z0: -1.0, z1: 1.0, k: x, material })
+    };
+    let xz_wall = |y: f32, material: Arc<dyn Scatter>| -> Box<dyn Hit> {
+        Box::new(XzRect { x0: -1.0, x1: 1.0, z0: -1.0, z1: 1.0, k: y, material })
+    };
+    vec![
+        yz_wall(-1.0, red),
+        yz_wall(1.0, green),
+        xz_wall(-1.0, Arc::clone(&white)),
+        xz_wall(1.0, Arc::clone(&white)),
+        Box::new(XyRect {
+            x0: -1.0,
+            x1: 1.0,
+            y0: -1.0,
+            y1: 1.0,
+            k: -1.0,
+            material: white,
+        }),
+        // The light hangs just below the ceiling.
+        Box::new(XzRect {
+            x0: -0.25,
+            x1: 0.25,
+            z0: -0.25,
+            z1: 0.25,
+            k: 0.999,
+            material: light,
+        }),
+    ]
+}
+
+fn rand_scene(seed: u64) -> Vec<Box<dyn Hit>> {
+    let n = 500;
+    let mut world: Vec<Box<dyn Hit>> = Vec::with_capacity(n);
+    world.push(Box::new(Plane {
+        point: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        normal: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+        material: Arc::new(Lambertian {
+            albedo: Box::new(SolidColor { color: Vec3 { x: 0.5, y: 0.5, z: 0.5 } }),
+        }),
+    }));
+    let mut sampler = Sampler::new(seed);
+    let glass: Arc<dyn Scatter> = Arc::new(Dielectric { refraction_index: 1.5 });
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let center = Vec3 {
+                x: a as f32 + 0.9 * sampler.next_f32(),
+                y: 0.2,
+                z: b as f32 + 0.9 * sampler.next_f32(),
+            };
+            if (center - Vec3 { x: 4.0, y: 0.2, z: 0.0 }).len() > 0.9 {
+                let r = sampler.next_f32();
+                if r < 0.8 {
+                    let color = Vec3 {
+                        x: sampler.next_f32() * sampler.next_f32(),
+                        y: sampler.next_f32() * sampler.next_f32(),
+                        z: sampler.next_f32() * sampler.next_f32(),
+                    };
+                    world.push(Box::new(Sphere {
+                        center,
+                        radius: 0.2,
+                        material: Arc::new(Lambertian {
+                            albedo: Box::new(SolidColor { color }),
+                        }),
+                    }));
+                } else if r < 0.95 {
+                    let color = Vec3 {
+                        x: 0.5 * (1.0 + sampler.next_f32()),
+                        y: 0.5 * (1.0 + sampler.next_f32()),
+                        z: 0.5 * (1.0 + sampler.next_f32()),
+                    };
+                    world.push(Box::new(Sphere {
+                        center,
+                        radius: 0.2,
+                        material: Arc::new(Reflective {
+                            albedo: Box::new(SolidColor { color }),
+                            fuzz: sampler.next_f32() * sampler.next_f32(),
+                        }),
+                    }));
+                } else {
+                    world.push(Box::new(Sphere {
+                        center,
+                        radius: 0.2,
+                        material: Arc::clone(&glass),
+                    }));
+                }
+            }
+        }
+    }
+
+    world.push(Box::new(Sphere {
+        center: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+        radius: 1.0,
+        material: glass,
+    }));
+    world.push(Box::new(Sphere {
+        center: Vec3 { x: -4.0, y: 1.0, z: 0.0 },
+        radius: 1.0,
+        material: Arc::new(Lambertian {
+            albedo: Box::new(SolidColor { color: Vec3 { x: 0.4, y: 0.2, z: 0.1 } }),
+        }),
+    }));
+    world.push(Box::new(Sphere {
+        center: Vec3 { x: 4.0, y: 1.0, z: 0.0 },
+        radius: 1.0,
+        material: Arc::new(Reflective {
+            albedo: Box::new(SolidColor {
+                color: Vec3 { x: 0.7, y: 0.6, z: 0.5 },
+            }),
+            fuzz: 0.0,
+        }),
+    }));
+
+    world
+}
@@ -1,12 +1,18 @@
+use std::sync::Arc;
+
+use raytracer::aabb::Aabb;
 use raytracer::hit::*;
+use raytracer::light::Light;
+use raytracer::onb::Onb;
 use raytracer::ray::*;
+use raytracer::sampler::Sampler;
 use raytracer::scatter::*;
 use raytracer::vec3::*;
 
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f32,
-    pub material: Box<dyn Scatter>,
+    pub material: Arc<dyn Scatter>,
 }
 
 impl Hit for Sphere {
@@ -16,41 +22,224 @@ impl Hit for Sphere {
         min: f32,
         max: f32,
     ) -> Option<HitRecord<'a>> {
-        // t^2*dot(B, B) + 2t*dot(B, A-C) + dot(A-C, A-C) - R^2 = 0 where:
-        // A = ray origin, B = ray direction, C = sphere center, R = sphere
-        // radius
-        let oc = ray.origin - self.center;
-        let a = dot(ray.direction, ray.direction);
-        let b = 2.0 * dot(ray.direction, oc);
-        let c = dot(oc, oc) - self.radius * self.radius;
-        let discriminant = b * b - 4.0 * a * c;
-
-        if discriminant > 0.0 {
-            // Solve the quadratic equation, which gives us the `t` offset with
-            // which when the ray is advanced, it will hit the sphere.
-            let solution = (-b - discriminant.sqrt()) / (2.0 * a);
-            if solution > min && solution < max {
-                let point = ray.point_at(solution);
-                return Some(HitRecord {
-                    t: solution,
-                    point: point,
-                    normal: (point - self.center).to_unit(),
-                    material: &*self.material,
-                });
-            }
-
-            let solution = (-b + discriminant.sqrt()) / (2.0 * a);
-            if solution > min && solution < max {
-                let point = ray.point_at(solution);
-                return Some(HitRecord {
-                    t: solution,
-                    point: point,
-                    normal: (point - self.center).to_unit(),
-                    material: &*self.material,
-                });
-            }
+        hit_sphere(self.center, self.radius, &*self.material, ray, min, max)
+    }
+
+    fn hit_all<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+        hits: &mut Vec<HitRecord<'a>>,
+    ) {
+        hit_sphere_all(self.center, self.radius, &*self.material, ray, min, max, hits)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3 { x: self.radius, y: self.radius, z: self.radius };
+        Some(Aabb {
+            min: self.center - r,
+            max: self.center + r,
+        })
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        if self.material.is_emissive() {
+            lights.push(Box::new(SphereLight {
+                center: self.center,
+                radius: self.radius,
+            }));
+        }
+    }
+}
+
+/// A sphere moving in a straight line from `center0` at `time0` to `center1`
+/// at `time1`. It rests at either end outside of that interval.
+///
+/// Unlike a `Sphere`, it is not sampled as a light if its material is
+/// emissive, since lights are sampled as if they stood still, but its light
+/// is still found by the rays scattered towards it.
+pub struct MovingSphere {
+    pub center0: Vec3,
+    pub center1: Vec3,
+    pub time0: f32,
+    pub time1: f32,
+    pub radius: f32,
+    pub material: Arc<dyn Scatter>,
+}
+
+impl MovingSphere {
+    pub fn center(&self, time: f32) -> Vec3 {
+        let t = if self.time1 > self.time0 {
+            ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.center0 + t * (self.center1 - self.center0)
+    }
+}
+
+impl Hit for MovingSphere {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        let center = self.center(ray.time);
+        hit_sphere(center, self.radius, &*self.material, ray, min, max)
+    }
+
+    fn hit_all<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+        hits: &mut Vec<HitRecord<'a>>,
+    ) {
+        let center = self.center(ray.time);
+        hit_sphere_all(center, self.radius, &*self.material, ray, min, max, hits)
+    }
+
+    /// Encloses the sphere along all of its path, so that it is found by rays
+    /// cast at any time.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3 { x: self.radius, y: self.radius, z: self.radius };
+        Some(Aabb::surrounding(
+            Aabb { min: self.center0 - r, max: self.center0 + r },
+            Aabb { min: self.center1 - r, max: self.center1 + r },
+        ))
+    }
+}
+
+fn hit_sphere<'a>(
+    center: Vec3,
+    radius: f32,
+    material: &'a dyn Scatter,
+    ray: &Ray,
+    min: f32,
+    max: f32,
+) -> Option<HitRecord<'a>> {
+    // The nearer solution is tried first.
+    let t = sphere_roots(center, radius, ray)?
+        .iter()
+        .cloned()
+        .find(|&t| t > min && t < max)?;
+    Some(sphere_record(center, material, ray, t))
+}
+
+fn hit_sphere_all<'a>(
+    center: Vec3,
+    radius: f32,
+    material: &'a dyn Scatter,
+    ray: &Ray,
+    min: f32,
+    max: f32,
+    hits: &mut Vec<HitRecord<'a>>,
+) {
+    if let Some(roots) = sphere_roots(center, radius, ray) {
+        for &t in roots.iter().filter(|&&t| t > min && t < max) {
+            hits.push(sphere_record(center, material, ray, t));
         }
+    }
+}
 
+/// Returns the offsets, nearer first, at which the ray enters and leaves the
+/// sphere, if it hits it at all.
+fn sphere_roots(center: Vec3, radius: f32, ray: &Ray) -> Option<[f32; 2]> {
+    // t^2*dot(B, B) + 2t*dot(B, A-C) + dot(A-C, A-C) - R^2 = 0 where:
+    // A = ray origin, B = ray direction, C = sphere center, R = sphere
+    // radius
+    let oc = ray.origin - center;
+    let a = dot(ray.direction, ray.direction);
+    let b = 2.0 * dot(ray.direction, oc);
+    let c = dot(oc, oc) - radius * radius;
+    let discriminant = b * b - 4.0 * a * c;
+
+    if discriminant > 0.0 {
+        // Solve the quadratic equation, which gives us the `t` offset with
+        // which when the ray is advanced, it will hit the sphere.
+        let root = discriminant.sqrt();
+        Some([(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)])
+    } else {
         None
     }
 }
+
+fn sphere_record<'a>(center: Vec3, material: &'a dyn Scatter, ray: &Ray, t: f32) -> HitRecord<'a> {
+    let point = ray.point_at(t);
+    let outward = (point - center).to_unit();
+    let (u, v) = sphere_uv(outward);
+    let (normal, front_face) = face_normal(ray, outward);
+    HitRecord {
+        t,
+        point,
+        normal,
+        front_face,
+        u,
+        v,
+        material,
+    }
+}
+
+/// Returns the surface coordinates of the point `p` on the unit sphere: `u` is
+/// the angle around the Y axis, starting from -X, and `v` the angle from the
+/// -Y pole to the +Y one, both scaled to range from 0 to 1.
+fn sphere_uv(p: Vec3) -> (f32, f32) {
+    let theta = (-p.y).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z).atan2(p.x) + std::f32::consts::PI;
+    (phi / (2.0 * std::f32::consts::PI), theta / std::f32::consts::PI)
+}
+
+/// A spherical light, sampled uniformly within the cone of directions in
+/// which it is seen from the point being lit.
+struct SphereLight {
+    center: Vec3,
+    radius: f32,
+}
+
+impl SphereLight {
+    /// Returns the cosine of the half angle of the cone that the sphere
+    /// subtends as seen from `origin`, or `None` if `origin` is inside it.
+    fn cos_theta_max(&self, origin: Vec3) -> Option<f32> {
+        let dist_squared = (self.center - origin).squared_len();
+        let r_squared = self.radius * self.radius;
+        if dist_squared <= r_squared {
+            None
+        } else {
+            Some((1.0 - r_squared / dist_squared).sqrt())
+        }
+    }
+}
+
+impl Light for SphereLight {
+    fn sample_direction(&self, origin: Vec3, sampler: &mut Sampler) -> Vec3 {
+        let to_center = self.center - origin;
+        let cos_theta_max = match self.cos_theta_max(origin) {
+            Some(cos) => cos,
+            // From inside the sphere it may be hit in any direction.
+            None => return sampler.in_unit_sphere(),
+        };
+        let r1 = sampler.next_f32();
+        let r2 = sampler.next_f32();
+        let z = 1.0 + r2 * (cos_theta_max - 1.0);
+        let phi = 2.0 * std::f32::consts::PI * r1;
+        let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+        let local = Vec3 { x: phi.cos() * sin_theta, y: phi.sin() * sin_theta, z };
+        Onb::from_w(to_center.to_unit()).local(local)
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f32 {
+        let cos_theta_max = match self.cos_theta_max(origin) {
+            Some(cos) => cos,
+            None => return 0.0,
+        };
+        // The direction must lie within the cone for the sphere to be hit.
+        let cos = dot(direction.to_unit(), (self.center - origin).to_unit());
+        if cos < cos_theta_max {
+            0.0
+        } else {
+            1.0 / (2.0 * std::f32::consts::PI * (1.0 - cos_theta_max))
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use raytracer::perlin::Perlin;
+use raytracer::vec3::Vec3;
+
+/// Describes the color of a material across an object's surface. Textures are
+/// evaluated at the surface coordinates `u` and `v` of a hit, or at the point
+/// hit itself, and are shared between render threads like the materials using
+/// them.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f32, v: f32, point: Vec3) -> Vec3;
+}
+
+/// Textures shared with `Arc` can be used by many materials while only being
+/// built once.
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: f32, v: f32, point: Vec3) -> Vec3 {
+        (**self).value(u, v, point)
+    }
+}
+
+/// A texture of a single color.
+pub struct SolidColor {
+    pub color: Vec3,
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f32, _v: f32, _point: Vec3) -> Vec3 {
+        self.color
+    }
+}
+
+/// A checkerboard filling space with cubes alternating between two textures.
+/// Since it depends on the point hit rather than the surface coordinates, it
+/// wraps around any object without distortion.
+pub struct Checker {
+    pub odd: Box<dyn Texture>,
+    pub even: Box<dyn Texture>,
+    /// The edge length of the cubes.
+    pub scale: f32,
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f32, v: f32, point: Vec3) -> Vec3 {
+        let cell = |x: f32| (x / self.scale).floor() as i64;
+        if (cell(point.x) + cell(point.y) + cell(point.z)) % 2 == 0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
+
+/// An image mapped onto a surface by its texture coordinates, with (0, 0) at
+/// the image's bottom left corner and (1, 1) at its top right. Coordinates
+/// outside that range repeat the image.
+///
+/// The pixels are shared between clones, so an image used by several
+/// materials is only kept in memory once.
+#[derive(Clone)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    /// Rows of pixels, top to bottom.
+    pixels: Arc<Vec<Vec3>>,
+}
+
+impl ImageTexture {
+    /// Loads the image at `path`, in any format supported by the `image`
+    /// crate.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, image::ImageError> {
+        let img = image::open(path)?.to_rgb8();
+        // Images are stored gamma corrected, so the gamma of 2 applied to the
+        // rendered image is undone to get back the linear colors.
+        let linear = |c: u8| (c as f32 / 255.0).powi(2);
+        let pixels = img
+            .pixels()
+            .map(|p| Vec3 {
+                x: linear(p[0]),
+                y: linear(p[1]),
+                z: linear(p[2]),
+            })
+            .collect();
+        Ok(Self {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            pixels: Arc::new(pixels),
+        })
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _point: Vec3) -> Vec3 {
+        if self.pixels.is_empty() {
+            return Vec3 { x: 0.0, y: 0.0, z: 0.0 };
+        }
+        let u = u - u.floor();
+        let v = v - v.floor();
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = (((1.0 - v) * self.height as f32) as usize).min(self.height - 1);
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// Marble-like veins: stripes along the z axis, given by a sine wave whose
+/// phase is perturbed by turbulence.
+pub struct Marble {
+    pub noise: Perlin,
+    /// The texture between the veins.
+    pub low: Box<dyn Texture>,
+    /// The texture of the veins.
+    pub high: Box<dyn Texture>,
+    /// The frequency of the noise and the stripes.
+    pub scale: f32,
+    /// How strongly the stripes are distorted.
+    pub turbulence: f32,
+    pub octaves: usize,
+}
+
+impl Texture for Marble {
+    fn value(&self, u: f32, v: f32, point: Vec3) -> Vec3 {
+        let p = self.scale * point;
+        let phase = p.z + self.turbulence * self.noise.turbulence(p, self.octaves);
+        let t = 0.5 * (1.0 + phase.sin());
+        blend(&*self.low, &*self.high, t, u, v, point)
+    }
+}
+
+/// The growth rings of wood: concentric cylinders around the y axis, whose
+/// radii are perturbed by turbulence.
+pub struct Wood {
+    pub noise: Perlin,
+    /// The texture at the inside of each ring.
+    pub low: Box<dyn Texture>,
+    /// The texture at the outside of each ring.
+    pub high: Box<dyn Texture>,
+    /// The frequency of the noise and the number of rings per unit of radius.
+    pub scale: f32,
+    /// How strongly the rings are distorted.
+    pub turbulence: f32,
+    pub octaves: usize,
+}
+
+impl Texture for Wood {
+    fn value(&self, u: f32, v: f32, point: Vec3) -> Vec3 {
+        let p = self.scale * point;
+        let distortion = self.turbulence * self.noise.turbulence(p, self.octaves);
+        let r = (p.x * p.x + p.z * p.z).sqrt() + distortion;
+        blend(&*self.low, &*self.high, r - r.floor(), u, v, point)
+    }
+}
+
+/// Clouds, or any other billowy pattern, given directly by turbulence.
+pub struct Clouds {
+    pub noise: Perlin,
+    /// The texture where the turbulence is lowest, such as the sky.
+    pub low: Box<dyn Texture>,
+    /// The texture where the turbulence is highest, such as the clouds.
+    pub high: Box<dyn Texture>,
+    /// The frequency of the noise.
+    pub scale: f32,
+    pub octaves: usize,
+}
+
+impl Texture for Clouds {
+    fn value(&self, u: f32, v: f32, point: Vec3) -> Vec3 {
+        let t = self.noise.turbulence(self.scale * point, self.octaves).min(1.0);
+        blend(&*self.low, &*self.high, t, u, v, point)
+    }
+}
+
+/// Interpolates linearly between the values of `low` and `high` by `t`.
+fn blend(low: &dyn Texture, high: &dyn Texture, t: f32, u: f32, v: f32, point: Vec3) -> Vec3 {
+    (1.0 - t) * low.value(u, v, point) + t * high.value(u, v, point)
+}
@@ -0,0 +1,97 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use raytracer::aabb::Aabb;
+use raytracer::hit::*;
+use raytracer::ray::*;
+use raytracer::roots::solve_quartic;
+use raytracer::scatter::*;
+use raytracer::vec3::*;
+
+/// A torus lying in the y = 0 plane of its own around `center`: the surface
+/// swept by a circle of `minor_radius` whose center goes around the y axis at
+/// `major_radius`. Its surface coordinates are the angle around the y axis,
+/// starting from -X like those of spheres, and the angle around the tube,
+/// starting from its inner edge.
+///
+/// Other orientations are had by transforming it.
+pub struct Torus {
+    pub center: Vec3,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    pub material: Arc<dyn Scatter>,
+}
+
+impl Hit for Torus {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        // The ray is intersected in double precision, as the quartic's
+        // coefficients grow with the fourth power of the distances involved.
+        let d = [ray.direction.x as f64, ray.direction.y as f64, ray.direction.z as f64];
+        let o = ray.origin - self.center;
+        let o = [o.x as f64, o.y as f64, o.z as f64];
+        let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+        // Starting the ray from its point nearest to the center keeps the
+        // coefficients small however far away it really starts.
+        let m = dot(d, d);
+        let t0 = -dot(o, d) / m;
+        let o = [o[0] + t0 * d[0], o[1] + t0 * d[1], o[2] + t0 * d[2]];
+
+        // (|p|^2 + R^2 - r^2)^2 = 4R^2(x^2 + z^2) for the points p along the
+        // ray, where R and r are the major and minor radii.
+        let (major, minor) = (self.major_radius as f64, self.minor_radius as f64);
+        let n = dot(o, d);
+        let k = dot(o, o) + major * major - minor * minor;
+        let r2 = 4.0 * major * major;
+        let roots = solve_quartic(
+            m * m,
+            4.0 * m * n,
+            4.0 * n * n + 2.0 * m * k - r2 * (d[0] * d[0] + d[2] * d[2]),
+            4.0 * n * k - 2.0 * r2 * (o[0] * d[0] + o[2] * d[2]),
+            k * k - r2 * (o[0] * o[0] + o[2] * o[2]),
+        );
+        let t = roots
+            .as_slice()
+            .iter()
+            .map(|&t| (t + t0) as f32)
+            .find(|&t| t > min && t < max)?;
+
+        let point = ray.point_at(t);
+        let local = point - self.center;
+        // The normal points away from the nearest point on the circle running
+        // through the middle of the tube.
+        let around = (local.x * local.x + local.z * local.z).sqrt();
+        let outward = if around > 0.0 {
+            let core = self.major_radius / around * Vec3 { x: local.x, y: 0.0, z: local.z };
+            (local - core).to_unit()
+        } else {
+            Vec3 { x: 0.0, y: local.y.signum(), z: 0.0 }
+        };
+        let (x, y, z) = (local.x as f64, local.y as f64, local.z as f64);
+        let u = ((-z).atan2(x) + PI) / (2.0 * PI);
+        let v = (y.atan2(around as f64 - major) + PI) / (2.0 * PI);
+        let (normal, front_face) = face_normal(ray, outward);
+        Some(HitRecord {
+            t,
+            point,
+            normal,
+            front_face,
+            u: u as f32,
+            v: v as f32,
+            material: &*self.material,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let outer = self.major_radius + self.minor_radius;
+        let r = Vec3 { x: outer, y: self.minor_radius, z: outer };
+        Some(Aabb {
+            min: self.center - r,
+            max: self.center + r,
+        })
+    }
+}
@@ -0,0 +1,147 @@
+use raytracer::aabb::Aabb;
+use raytracer::hit::*;
+use raytracer::light::Light;
+use raytracer::mat4::Mat4;
+use raytracer::ray::Ray;
+use raytracer::sampler::Sampler;
+use raytracer::vec3::*;
+
+/// An object placed in the world by an affine transformation, such that it
+/// may be translated, rotated and scaled without changing the object itself.
+///
+/// Rather than transforming the object, rays are transformed into the
+/// object's own space, and the hits found there back into the world.
+pub struct Transformed<T: Hit> {
+    object: T,
+    to_world: Mat4,
+    to_object: Mat4,
+    /// The inverse transpose of `to_world`, by which normals are transformed.
+    /// They stay perpendicular to the surface that way, whereas transforming
+    /// them like the surface would skew them under non-uniform scaling.
+    normal_to_world: Mat4,
+    bbox: Option<Aabb>,
+}
+
+impl<T: Hit> Transformed<T> {
+    /// Places `object` in the world with `transform`, which maps points in
+    /// the object's space to the world's.
+    ///
+    /// # Panics
+    ///
+    /// If `transform` cannot be inverted.
+    pub fn new(object: T, transform: Mat4) -> Self {
+        let to_object = transform
+            .inverse()
+            .expect("transformation is not invertible");
+        // The transformed box is enclosed by the box around its transformed
+        // corners.
+        let bbox = object.bounding_box().map(|b| {
+            let corner = |i: usize| Vec3 {
+                x: if i & 1 == 0 { b.min.x } else { b.max.x },
+                y: if i & 2 == 0 { b.min.y } else { b.max.y },
+                z: if i & 4 == 0 { b.min.z } else { b.max.z },
+            };
+            let p = transform.transform_point(corner(0));
+            (1..8).fold(Aabb { min: p, max: p }, |acc, i| {
+                let p = transform.transform_point(corner(i));
+                Aabb::surrounding(acc, Aabb { min: p, max: p })
+            })
+        });
+        Self {
+            object,
+            to_world: transform,
+            to_object,
+            normal_to_world: to_object.transpose(),
+            bbox,
+        }
+    }
+}
+
+impl<T: Hit> Transformed<T> {
+    /// Transforms `ray` into the object's space. The direction is not
+    /// normalized, so that the offset `t` along the ray is the same in both
+    /// spaces.
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        Ray {
+            origin: self.to_object.transform_point(ray.origin),
+            direction: self.to_object.transform_vector(ray.direction),
+            time: ray.time,
+        }
+    }
+
+    /// Transforms a hit of the local ray back into the world.
+    fn to_world(&self, ray: &Ray, rec: &mut HitRecord) {
+        rec.point = ray.point_at(rec.t);
+        rec.normal = self.normal_to_world.transform_vector(rec.normal).to_unit();
+    }
+}
+
+impl<T: Hit> Hit for Transformed<T> {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        let mut rec = self.object.hit(&self.local_ray(ray), min, max)?;
+        self.to_world(ray, &mut rec);
+        Some(rec)
+    }
+
+    fn hit_all<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+        hits: &mut Vec<HitRecord<'a>>,
+    ) {
+        let first = hits.len();
+        self.object.hit_all(&self.local_ray(ray), min, max, hits);
+        for rec in hits[first..].iter_mut() {
+            self.to_world(ray, rec);
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bbox
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        let mut local = Vec::new();
+        self.object.lights(&mut local);
+        for light in local {
+            lights.push(Box::new(TransformedLight {
+                light,
+                to_world: self.to_world,
+                to_object: self.to_object,
+                determinant: self.to_object.linear_determinant().abs(),
+            }));
+        }
+    }
+}
+
+/// A light of a transformed object, sampled in the object's space.
+struct TransformedLight {
+    light: Box<dyn Light>,
+    to_world: Mat4,
+    to_object: Mat4,
+    determinant: f32,
+}
+
+impl Light for TransformedLight {
+    fn sample_direction(&self, origin: Vec3, sampler: &mut Sampler) -> Vec3 {
+        let local_origin = self.to_object.transform_point(origin);
+        let direction = self.light.sample_direction(local_origin, sampler);
+        self.to_world.transform_vector(direction)
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f32 {
+        let local_origin = self.to_object.transform_point(origin);
+        let local_direction = self.to_object.transform_vector(direction.to_unit());
+        // Mapping the unit direction `w` to `Mw / |Mw|` changes the density of
+        // directions by a factor of `|det M| / |Mw|^3`, which is one for
+        // rotations but not under scaling.
+        let len = local_direction.len();
+        self.light.pdf(local_origin, local_direction) * self.determinant / (len * len * len)
+    }
+}
@@ -0,0 +1,354 @@
+use std::sync::Arc;
+
+use raytracer::aabb::Aabb;
+use raytracer::hit::*;
+use raytracer::light::Light;
+use raytracer::ray::*;
+use raytracer::sah_bvh::*;
+use raytracer::sampler::Sampler;
+use raytracer::scatter::*;
+use raytracer::vec3::*;
+
+/// Rays nearly parallel to a triangle's plane are considered to miss it.
+const PARALLEL_EPSILON: f32 = 1e-8;
+/// Flat triangles have zero extent along at least one axis, so their bounding
+/// boxes are padded by this much to still be hit by rays.
+const BBOX_PADDING: f32 = 1e-4;
+
+pub struct Triangle {
+    pub v0: Vec3,
+    pub v1: Vec3,
+    pub v2: Vec3,
+    pub material: Arc<dyn Scatter>,
+}
+
+impl Hit for Triangle {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        // Without texture coordinates of its own, the triangle's barycentric
+        // coordinates are used.
+        let (t, u, v) = intersect(ray, self.v0, self.v1, self.v2, min, max)?;
+        let (normal, front_face) =
+            face_normal(ray, cross(self.v1 - self.v0, self.v2 - self.v0).to_unit());
+        Some(HitRecord {
+            t,
+            point: ray.point_at(t),
+            normal,
+            front_face,
+            u,
+            v,
+            material: &*self.material,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(triangle_bbox(self.v0, self.v1, self.v2))
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        if self.material.is_emissive() {
+            lights.push(Box::new(TriangleLight {
+                v0: self.v0,
+                v1: self.v1,
+                v2: self.v2,
+            }));
+        }
+    }
+}
+
+/// A triangular light, sampled uniformly over its area.
+struct TriangleLight {
+    v0: Vec3,
+    v1: Vec3,
+    v2: Vec3,
+}
+
+impl Light for TriangleLight {
+    fn sample_direction(&self, origin: Vec3, sampler: &mut Sampler) -> Vec3 {
+        // Folding the unit square onto the triangle with the square root
+        // distributes the points uniformly.
+        let s = sampler.next_f32().sqrt();
+        let t = sampler.next_f32();
+        let u = 1.0 - s;
+        let v = t * s;
+        let point = self.v0 + u * (self.v1 - self.v0) + v * (self.v2 - self.v0);
+        point - origin
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f32 {
+        let ray = Ray { origin, direction: direction.to_unit(), time: 0.0 };
+        match intersect(&ray, self.v0, self.v1, self.v2, 0.001, f32::MAX) {
+            Some((t, _, _)) => {
+                // Convert the density over the triangle's area to one over
+                // solid angle.
+                let n = cross(self.v1 - self.v0, self.v2 - self.v0);
+                let area = 0.5 * n.len();
+                let cos = dot(ray.direction, n.to_unit()).abs();
+                if cos > 0.0 {
+                    t * t / (cos * area)
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        }
+    }
+}
+
+/// The vertex data of a `TriangleMesh`, shared by all its triangles.
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    /// Optional per-vertex normals, indexed the same way as `positions`. If
+    /// present, they are interpolated across each triangle to give the mesh a
+    /// smooth appearance.
+    pub normals: Option<Vec<Vec3>>,
+    /// Optional per-vertex texture coordinates, indexed the same way as
+    /// `positions`. Without them, textures are mapped using each triangle's
+    /// barycentric coordinates.
+    pub uvs: Option<Vec<[f32; 2]>>,
+    /// Each triangle is described by the indices of its three vertices, in
+    /// counter-clockwise order when looking at its front face.
+    pub indices: Vec<[usize; 3]>,
+    pub material: Arc<dyn Scatter>,
+}
+
+/// A mesh of triangles sharing their vertices. The triangles are kept in their
+/// own bounding volume hierarchy, so a mesh can be placed in a scene like any
+/// other object.
+pub struct TriangleMesh {
+    bvh: SahBvh<MeshTriangle>,
+}
+
+impl TriangleMesh {
+    pub fn new(data: MeshData) -> Self {
+        let n_triangles = data.indices.len();
+        let data = Arc::new(data);
+        let triangles = (0..n_triangles)
+            .map(|index| MeshTriangle {
+                mesh: Arc::clone(&data),
+                index,
+            })
+            .collect();
+        Self {
+            bvh: SahBvh::new(triangles, SahInfo::default()),
+        }
+    }
+}
+
+impl Hit for TriangleMesh {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        self.bvh.hit(ray, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounding_box()
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        self.bvh.lights(lights)
+    }
+}
+
+/// A single triangle of a `TriangleMesh`, referring to its vertices by index.
+struct MeshTriangle {
+    mesh: Arc<MeshData>,
+    index: usize,
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> (Vec3, Vec3, Vec3) {
+        let [i0, i1, i2] = self.mesh.indices[self.index];
+        let p = &self.mesh.positions;
+        (p[i0], p[i1], p[i2])
+    }
+}
+
+impl Hit for MeshTriangle {
+    fn hit<'a, 'b: 'a>(
+        &'b self,
+        ray: &Ray,
+        min: f32,
+        max: f32,
+    ) -> Option<HitRecord<'a>> {
+        let (v0, v1, v2) = self.vertices();
+        let (t, b1, b2) = intersect(ray, v0, v1, v2, min, max)?;
+        // Vertex attributes are interpolated using the hit's barycentric
+        // coordinates.
+        let [i0, i1, i2] = self.mesh.indices[self.index];
+        let b0 = 1.0 - b1 - b2;
+        // The side hit is that of the triangle itself, since interpolated
+        // normals may face the other way near silhouettes, and the normal used
+        // for shading is flipped to that side.
+        let (geometric, front_face) = face_normal(ray, cross(v1 - v0, v2 - v0).to_unit());
+        let normal = match self.mesh.normals {
+            Some(ref normals) => {
+                let normal = (b0 * normals[i0] + b1 * normals[i1] + b2 * normals[i2]).to_unit();
+                if dot(normal, geometric) < 0.0 {
+                    -1.0 * normal
+                } else {
+                    normal
+                }
+            }
+            None => geometric,
+        };
+        let (u, v) = match self.mesh.uvs {
+            Some(ref uvs) => (
+                b0 * uvs[i0][0] + b1 * uvs[i1][0] + b2 * uvs[i2][0],
+                b0 * uvs[i0][1] + b1 * uvs[i1][1] + b2 * uvs[i2][1],
+            ),
+            None => (b1, b2),
+        };
+        Some(HitRecord {
+            t,
+            point: ray.point_at(t),
+            normal,
+            front_face,
+            u,
+            v,
+            material: &*self.mesh.material,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (v0, v1, v2) = self.vertices();
+        Some(triangle_bbox(v0, v1, v2))
+    }
+
+    fn lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+        if self.mesh.material.is_emissive() {
+            let (v0, v1, v2) = self.vertices();
+            lights.push(Box::new(TriangleLight { v0, v1, v2 }));
+        }
+    }
+}
+
+/// Intersects the ray with the triangle using the Möller–Trumbore algorithm,
+/// returning the ray's offset and the barycentric coordinates `u` and `v` of
+/// the hit, which weigh `v1` and `v2` respectively.
+fn intersect(
+    ray: &Ray,
+    v0: Vec3,
+    v1: Vec3,
+    v2: Vec3,
+    min: f32,
+    max: f32,
+) -> Option<(f32, f32, f32)> {
+    // Any point on the triangle is v0 + u*e1 + v*e2, so the hit satisfies
+    // origin + t*direction = v0 + u*e1 + v*e2, a linear system in t, u and v
+    // that is solved with Cramer's rule.
+    let e1 = v1 - v0;
+    let e2 = v2 - v0;
+    let p = cross(ray.direction, e2);
+    let det = dot(e1, p);
+    if det.abs() < PARALLEL_EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let s = ray.origin - v0;
+    let u = dot(s, p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = cross(s, e1);
+    let v = dot(ray.direction, q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = dot(e2, q) * inv_det;
+    if t > min && t < max {
+        Some((t, u, v))
+    } else {
+        None
+    }
+}
+
+fn triangle_bbox(v0: Vec3, v1: Vec3, v2: Vec3) -> Aabb {
+    let pad = Vec3 { x: BBOX_PADDING, y: BBOX_PADDING, z: BBOX_PADDING };
+    let bbox = Aabb::surrounding(Aabb { min: v0, max: v0 }, Aabb { min: v1, max: v1 });
+    let bbox = Aabb::surrounding(bbox, Aabb { min: v2, max: v2 });
+    Aabb {
+        min: bbox.min - pad,
+        max: bbox.max + pad,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raytracer::light::LightList;
+    use raytracer::texture::SolidColor;
+
+    fn vec3(x: f32, y: f32, z: f32) -> Vec3 {
+        Vec3 { x, y, z }
+    }
+
+    /// A unit square in the xy plane facing +z, split into two triangles.
+    fn square(normals: Option<Vec<Vec3>>, material: Arc<dyn Scatter>) -> TriangleMesh {
+        TriangleMesh::new(MeshData {
+            positions: vec![
+                vec3(0.0, 0.0, 0.0),
+                vec3(1.0, 0.0, 0.0),
+                vec3(1.0, 1.0, 0.0),
+                vec3(0.0, 1.0, 0.0),
+            ],
+            normals,
+            uvs: None,
+            indices: vec![[0, 1, 2], [0, 2, 3]],
+            material,
+        })
+    }
+
+    fn light() -> Arc<dyn Scatter> {
+        let color = vec3(4.0, 4.0, 4.0);
+        Arc::new(DiffuseLight { emit: Box::new(SolidColor { color }) })
+    }
+
+    #[test]
+    fn emissive_meshes_are_lights() {
+        let mesh = square(None, light());
+        let mut lights = Vec::new();
+        mesh.lights(&mut lights);
+        assert_eq!(lights.len(), 2);
+
+        // Together, the triangles sample the square as a whole.
+        let origin = vec3(0.5, 0.5, 1.0);
+        let direction = vec3(0.2, 0.3, 0.0) - origin;
+        let pdf = LightList::new(&mesh).pdf(origin, direction);
+        let expected = direction.len() * direction.len() / (direction.to_unit().z.abs());
+        assert!((pdf - expected).abs() < 1e-3 * expected, "{} != {}", pdf, expected);
+    }
+
+    #[test]
+    fn front_face_follows_the_triangle_not_its_normals() {
+        // Normals tilted so far that one of them points away from the front.
+        let normals = vec![
+            vec3(0.0, 0.0, 1.0),
+            vec3(2.0, 0.0, 1.0).to_unit(),
+            vec3(-1.0, 0.0, -0.2).to_unit(),
+            vec3(0.0, 0.0, 1.0),
+        ];
+        let mesh = square(Some(normals), light());
+        for &(z, front_face) in [(1.0, true), (-1.0, false)].iter() {
+            // Straight at the point where the interpolated normal is flipped.
+            let ray = Ray {
+                origin: vec3(0.9, 0.8, z),
+                direction: vec3(0.0, 0.0, -z),
+                time: 0.0,
+            };
+            let hit = mesh.hit(&ray, 0.001, f32::MAX).unwrap();
+            assert_eq!(hit.front_face, front_face);
+            assert!(dot(hit.normal, ray.direction) < 0.0);
+        }
+    }
+}
@@ -25,8 +25,8 @@ impl Vec3 {
         self.x.powi(2) + self.y.powi(2) + self.z.powi(2)
     }
 
-    pub fn to_unit(&self) -> Vec3 {
-        *self / self.len()
+    pub fn to_unit(self) -> Vec3 {
+        self / self.len()
     }
 }
 